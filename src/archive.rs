@@ -0,0 +1,106 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{Read, Write},
+};
+
+use oci_client::{
+    client::{ImageData, ImageLayer},
+    manifest::OciImageManifest,
+};
+
+use crate::{
+    client::build_manifest,
+    layout::{
+        blob_path, build_image_data, check_layout_version, layout_files, parse_manifest,
+        select_manifest, INDEX_FILE, OCI_LAYOUT_FILE,
+    },
+    Error, Result, ToConfig, WasmConfig,
+};
+
+/// Writes a wasm artifact as a tarball in the [OCI image
+/// layout](https://github.com/opencontainers/image-spec/blob/main/image-layout.md) format (the
+/// same format produced by `docker save` or `oras`' `oci-archive` output). This takes the same
+/// arguments as [`WasmClient::push`](crate::WasmClient::push), so the archive contains exactly
+/// the manifest that would have been pushed, byte for byte. If `ref_name` is set, the manifest is
+/// annotated with it in the archive's `index.json`.
+///
+/// All entries are written with a zeroed modification time so identical inputs produce identical
+/// archives. Returns the digest of the manifest, which is the same digest `push` produces
+pub fn write_image_archive(
+    writer: impl Write,
+    component_layer: ImageLayer,
+    config: impl ToConfig,
+    annotations: Option<BTreeMap<String, String>>,
+    ref_name: Option<&str>,
+) -> Result<String> {
+    let layers = vec![component_layer];
+    let config = config.to_config()?;
+    let manifest = build_manifest(&layers, &config, annotations);
+    let image_data = ImageData {
+        layers,
+        digest: None,
+        config,
+        manifest: Some(manifest),
+    };
+    let (files, digest) = layout_files(&image_data, None, ref_name, None)?;
+
+    let mut builder = tar::Builder::new(writer);
+    for (path, data) in files {
+        let mut header = tar::Header::new_ustar();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(0);
+        builder.append_data(&mut header, path, data.as_slice())?;
+    }
+    builder.into_inner()?.flush()?;
+    Ok(digest)
+}
+
+/// Reads a wasm artifact from a tarball in the OCI image layout format, such as one written by
+/// [`write_image_archive`]. If `ref_name` is set, the manifest annotated with that
+/// `org.opencontainers.image.ref.name` is loaded, otherwise the archive must contain exactly one
+/// manifest.
+///
+/// All blobs are verified against their digests and the artifact is validated with the same rules
+/// as [`WasmClient::pull_and_verify`](crate::WasmClient::pull_and_verify). The returned values can
+/// be passed straight back to [`WasmClient::push`](crate::WasmClient::push)
+pub fn read_image_archive(
+    reader: impl Read,
+    ref_name: Option<&str>,
+) -> Result<(OciImageManifest, WasmConfig, ImageLayer)> {
+    let mut archive = tar::Archive::new(reader);
+    let mut files = HashMap::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.to_string_lossy().into_owned();
+        // The size comes from the untrusted tar header, so don't preallocate based on it
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+        files.insert(path.trim_start_matches("./").to_string(), data);
+    }
+    let mut take = |path: &str| {
+        files
+            .remove(path)
+            .ok_or_else(|| Error::InvalidLayout(format!("missing {path}")))
+    };
+
+    check_layout_version(&take(OCI_LAYOUT_FILE)?)?;
+    let entry = select_manifest(&take(INDEX_FILE)?, ref_name)?;
+    let manifest = parse_manifest(&take(&blob_path(&entry.digest)?)?, &entry.digest)?;
+    let config = take(&blob_path(&manifest.config.digest)?)?;
+    let layer = take(&blob_path(&manifest.layers[0].digest)?)?;
+    let (mut image_data, config) = build_image_data(manifest, entry.digest, config, layer)?;
+
+    let manifest = image_data
+        .manifest
+        .take()
+        .expect("manifest is always set when building image data");
+    let layer = image_data
+        .layers
+        .pop()
+        .expect("image data always contains exactly one layer");
+    Ok((manifest, config, layer))
+}
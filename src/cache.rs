@@ -0,0 +1,263 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use chrono::{DateTime, Utc};
+use oci_client::{manifest::OciImageManifest, Reference};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+use crate::{config::sha256_digest, layout::blob_path, Error, Result};
+
+const BLOBS_DIR: &str = "blobs";
+const MANIFESTS_DIR: &str = "manifests";
+const REFS_FILE: &str = "refs.json";
+
+/// Serializes updates of the refs index within this process so concurrent pulls don't overwrite
+/// each other's entries
+static REFS_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Configuration for a [`Cache`]
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    /// The directory to store cached data in. It is created if it doesn't exist
+    pub dir: PathBuf,
+    /// The maximum total size in bytes of all cached manifests and blobs. When exceeded, the least
+    /// recently used entries are evicted
+    pub max_size: u64,
+    /// How long a tag is trusted to point at the same manifest before it is resolved against the
+    /// registry again. References by digest never expire
+    pub tag_ttl: Duration,
+}
+
+impl CacheConfig {
+    /// Creates a new config for a cache in the given directory, with a size limit of 10 GiB and a
+    /// tag TTL of 5 minutes
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_size: 10 * 1024 * 1024 * 1024,
+            tag_ttl: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RefEntry {
+    digest: String,
+    resolved: DateTime<Utc>,
+}
+
+/// A content-addressed, on-disk cache of pulled wasm artifacts. Config and layer blobs are stored
+/// by digest and verified every time they are read, as are manifests, which are stored byte for
+/// byte as the registry returned them. References are mapped to manifest digests in an index whose
+/// tag entries expire after [`CacheConfig::tag_ttl`].
+///
+/// Attach a cache to a client with [`WasmClient::with_cache`](crate::WasmClient::with_cache)
+#[derive(Debug, Clone)]
+pub struct Cache {
+    config: CacheConfig,
+}
+
+impl Cache {
+    /// Creates a new cache with the given config
+    pub fn new(config: CacheConfig) -> Self {
+        Self { config }
+    }
+
+    /// Returns the config for this cache
+    pub fn config(&self) -> &CacheConfig {
+        &self.config
+    }
+
+    /// Removes everything from the cache
+    pub async fn clear(&self) -> Result<()> {
+        match tokio::fs::remove_dir_all(&self.config.dir).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the cached manifest digest for the given reference, if there is one that hasn't
+    /// expired
+    pub(crate) async fn get_ref(&self, image: &Reference) -> Result<Option<String>> {
+        let refs = self.read_refs().await?;
+        let Some(entry) = refs.get(&image.whole()) else {
+            return Ok(None);
+        };
+        let age = (Utc::now() - entry.resolved).to_std().unwrap_or_default();
+        if image.digest().is_none() && age >= self.config.tag_ttl {
+            return Ok(None);
+        }
+        Ok(Some(entry.digest.clone()))
+    }
+
+    /// Records the manifest digest the given reference resolved to
+    pub(crate) async fn put_ref(&self, image: &Reference, digest: &str) -> Result<()> {
+        let _guard = REFS_LOCK.lock().await;
+        let mut refs = self.read_refs().await?;
+        refs.insert(
+            image.whole(),
+            RefEntry {
+                digest: digest.to_string(),
+                resolved: Utc::now(),
+            },
+        );
+        let data = serde_json::to_vec(&refs).map_err(Error::ManifestSerialize)?;
+        write_atomic(&self.config.dir.join(REFS_FILE), &data).await
+    }
+
+    /// Returns the cached manifest with the given digest. Manifests that don't match their digest
+    /// are removed and treated as a miss
+    pub(crate) async fn get_manifest(&self, digest: &str) -> Result<Option<OciImageManifest>> {
+        let path = self.config.dir.join(MANIFESTS_DIR).join(blob_path(digest)?);
+        let Some(data) = read_and_touch(&path).await? else {
+            return Ok(None);
+        };
+        if sha256_digest(&data) != digest {
+            let _ = tokio::fs::remove_file(&path).await;
+            return Ok(None);
+        }
+        // A manifest that can't be parsed is treated as a miss so it gets replaced
+        Ok(serde_json::from_slice(&data).ok())
+    }
+
+    /// Stores the raw manifest with the given digest, exactly as the registry returned it
+    pub(crate) async fn put_manifest(&self, digest: &str, data: &[u8]) -> Result<()> {
+        let path = self.config.dir.join(MANIFESTS_DIR).join(blob_path(digest)?);
+        write_content(&path, data).await?;
+        self.evict().await
+    }
+
+    /// Returns the cached blob with the given digest. Blobs that don't match their digest are
+    /// removed and treated as a miss
+    pub(crate) async fn get_blob(&self, digest: &str) -> Result<Option<Vec<u8>>> {
+        let path = self.config.dir.join(BLOBS_DIR).join(blob_path(digest)?);
+        let Some(data) = read_and_touch(&path).await? else {
+            return Ok(None);
+        };
+        if sha256_digest(&data) != digest {
+            let _ = tokio::fs::remove_file(&path).await;
+            return Ok(None);
+        }
+        Ok(Some(data))
+    }
+
+    /// Stores the blob with the given digest
+    pub(crate) async fn put_blob(&self, digest: &str, data: &[u8]) -> Result<()> {
+        let path = self.config.dir.join(BLOBS_DIR).join(blob_path(digest)?);
+        write_content(&path, data).await?;
+        self.evict().await
+    }
+
+    async fn read_refs(&self) -> Result<BTreeMap<String, RefEntry>> {
+        match tokio::fs::read(self.config.dir.join(REFS_FILE)).await {
+            // A corrupt index is treated as empty so it gets rebuilt
+            Ok(data) => Ok(serde_json::from_slice(&data).unwrap_or_default()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Removes the least recently used manifests and blobs until the cache fits in
+    /// [`CacheConfig::max_size`]
+    async fn evict(&self) -> Result<()> {
+        let mut entries = Vec::new();
+        for dir in [BLOBS_DIR, MANIFESTS_DIR] {
+            let dir = self.config.dir.join(dir).join("sha256");
+            let mut read_dir = match tokio::fs::read_dir(&dir).await {
+                Ok(read_dir) => read_dir,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            while let Some(entry) = read_dir.next_entry().await? {
+                // Skip files that are still being written or were removed by a concurrent eviction
+                if entry.path().extension().is_some_and(|ext| ext == "tmp") {
+                    continue;
+                }
+                let metadata = match entry.metadata().await {
+                    Ok(metadata) => metadata,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                    Err(e) => return Err(e.into()),
+                };
+                if metadata.is_file() {
+                    let used = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                    entries.push((used, metadata.len(), entry.path()));
+                }
+            }
+        }
+
+        let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        entries.sort_by_key(|(used, _, _)| *used);
+        for (_, size, path) in entries {
+            if total <= self.config.max_size {
+                break;
+            }
+            match tokio::fs::remove_file(&path).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => total -= size,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads the file at the given path, updating its modification time so it counts as recently used
+async fn read_and_touch(path: &Path) -> Result<Option<Vec<u8>>> {
+    let data = match tokio::fs::read(path).await {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    if let Ok(file) = tokio::fs::File::options().write(true).open(path).await {
+        let _ = file.into_std().await.set_modified(SystemTime::now());
+    }
+    Ok(Some(data))
+}
+
+/// Writes the file by writing to a temporary file and renaming it, so readers never see partially
+/// written data
+async fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let (mut file, tmp) = create_tmp_file(path).await?;
+    if let Err(e) = file.write_all(data).await {
+        let _ = tokio::fs::remove_file(&tmp).await;
+        return Err(e.into());
+    }
+    drop(file);
+    if let Err(e) = tokio::fs::rename(&tmp, path).await {
+        let _ = tokio::fs::remove_file(&tmp).await;
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// Creates a new temporary file next to the given path, opened for reading and writing, and
+/// returns it along with its path. The name is random and the file must not exist yet, so a file
+/// or symlink planted in a shared directory is never written to. Its extension is always `tmp`
+pub(crate) async fn create_tmp_file(path: &Path) -> std::io::Result<(tokio::fs::File, PathBuf)> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".{:016x}.tmp", OsRng.next_u64()));
+    let tmp = PathBuf::from(tmp);
+    let file = tokio::fs::File::options()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&tmp)
+        .await?;
+    Ok((file, tmp))
+}
+
+/// Same as [`write_atomic`], but for content-addressed paths. If another writer won the race and
+/// the file already exists, it has the same contents, so the failed write counts as a success
+async fn write_content(path: &Path, data: &[u8]) -> Result<()> {
+    match write_atomic(path, data).await {
+        Err(_) if tokio::fs::try_exists(path).await.unwrap_or(false) => Ok(()),
+        res => res,
+    }
+}
@@ -1,20 +1,52 @@
-use std::{collections::BTreeMap, ops::Deref};
+use std::{collections::BTreeMap, ops::Deref, path::Path};
 
+use bytes::BytesMut;
+use futures_util::{Stream, TryStreamExt};
+use http::HeaderValue;
 use oci_client::{
-    client::{ImageData, ImageLayer, PushResponse},
-    manifest::OciImageManifest,
+    client::{ClientConfig, Config, ImageData, ImageLayer, PushResponse},
+    errors::{DigestError, OciDistributionError},
+    manifest::{
+        ImageIndexEntry, OciDescriptor, OciImageIndex, OciImageManifest, OciManifest, Platform,
+        OCI_IMAGE_INDEX_MEDIA_TYPE,
+    },
     secrets::RegistryAuth,
-    Client, Reference,
+    Client, Reference, RegistryOperation,
 };
+use oci_spec::image::{Arch, Os};
+use semver::VersionReq;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+use wit_parser::{Resolve, WorldId};
 
 use crate::{
-    config::ToConfig, WasmConfig, WASM_LAYER_MEDIA_TYPE, WASM_MANIFEST_CONFIG_MEDIA_TYPE,
-    WASM_MANIFEST_MEDIA_TYPE,
+    cache::{create_tmp_file, Cache},
+    config::{sha256_digest, sha256_digest_file, ToConfig, STREAM_CHUNK_SIZE},
+    embedded_signature::{verify_embedded_signature, verify_embedded_signature_stream},
+    layout::write_layout,
+    platform::{select_platform, wasm_platform_resolver},
+    referrers::{
+        fallback_tag, is_not_found, Referrer, ReferrerDescriptor, ReferrersIndex, EMPTY_DATA,
+        EMPTY_MEDIA_TYPE,
+    },
+    signature::{
+        decode_signature, encode_signature, signature_tag, SigningKey, SimpleSigning, VerifyingKey,
+        MAX_SIMPLE_SIGNING_SIZE, SIGNATURE_ANNOTATION, SIGNATURE_CONFIG_MEDIA_TYPE,
+        SIMPLE_SIGNING_MEDIA_TYPE,
+    },
+    version::resolve_tag,
+    Error, Result, WasmConfig, WasmOs, WASM_ARCHITECTURE, WASM_LAYER_MEDIA_TYPE,
+    WASM_MANIFEST_CONFIG_MEDIA_TYPE, WASM_MANIFEST_MEDIA_TYPE,
 };
 
+/// The number of tags requested per page when listing tags
+const TAG_PAGE_SIZE: usize = 1000;
+
 /// A light wrapper around the oci-distribution client to add support for the `application/wasm` type
 pub struct WasmClient {
     client: Client,
+    cache: Option<Cache>,
+    required_world: Option<(Resolve, WorldId)>,
+    trusted_keys: Vec<VerifyingKey>,
 }
 
 impl AsRef<Client> for WasmClient {
@@ -33,7 +65,12 @@ impl Deref for WasmClient {
 
 impl From<Client> for WasmClient {
     fn from(value: Client) -> Self {
-        Self { client: value }
+        Self {
+            client: value,
+            cache: None,
+            required_world: None,
+            trusted_keys: Vec::new(),
+        }
     }
 }
 
@@ -49,53 +86,152 @@ impl WasmClient {
         Self::from(client)
     }
 
-    /// A convenience wrapper around [`Client::pull`] that pulls a wasm component and errors if
-    /// there are layers that aren't wasm
-    pub async fn pull(&self, image: &Reference, auth: &RegistryAuth) -> anyhow::Result<ImageData> {
-        let image_data = self
-            .client
-            .pull(image, auth, vec![WASM_LAYER_MEDIA_TYPE])
-            .await?;
-        if image_data.layers.len() != 1 {
-            anyhow::bail!("Wasm components must have exactly one layer");
-        }
+    /// Create a new client with the default [`ClientConfig`], except that image indexes are
+    /// resolved with [`wasm_platform_resolver`] so pulling a multi-platform wasm index selects the
+    /// `wasip2` entry, falling back to `wasip1`
+    pub fn new_with_defaults() -> Self {
+        Self::new(Client::new(ClientConfig {
+            platform_resolver: Some(Box::new(wasm_platform_resolver)),
+            ..Default::default()
+        }))
+    }
+
+    /// Attaches an on-disk [`Cache`] to this client. When set, [`WasmClient::pull`] and
+    /// [`WasmClient::pull_manifest_and_config`] consult the cache before contacting the registry
+    /// and store everything they pull in it
+    #[must_use]
+    pub fn with_cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Returns the cache attached to this client, if any
+    pub fn cache(&self) -> Option<&Cache> {
+        self.cache.as_ref()
+    }
+
+    /// Requires every component pushed with [`WasmClient::push`] or
+    /// [`WasmClient::push_from_file`] to conform to the given world, as checked by
+    /// [`Component::satisfies_world`](crate::Component::satisfies_world). Pushes of non-conforming
+    /// components fail with [`Error::WorldConformance`] before anything is uploaded. Configs
+    /// without component information are treated as a component with no imports or exports
+    #[must_use]
+    pub fn with_required_world(mut self, resolve: Resolve, world_id: WorldId) -> Self {
+        self.required_world = Some((resolve, world_id));
+        self
+    }
 
-        if image_data.config.media_type != WASM_MANIFEST_CONFIG_MEDIA_TYPE {
-            anyhow::bail!(
-                "Wasm components must have a config of type {}",
-                WASM_MANIFEST_CONFIG_MEDIA_TYPE
-            );
+    /// Requires every layer pulled with [`WasmClient::pull`], [`WasmClient::pull_to_writer`],
+    /// [`WasmClient::pull_to_file`] and the methods built on them to carry a signature embedded in
+    /// its `signature` custom section made by one of the given keys, such as the ones added by
+    /// [`WasmConfig::from_raw_component_signed`]. Pulls of unsigned layers or layers signed by
+    /// other keys fail with [`Error::NoValidSignature`]. If the config records a `signerKeyId`,
+    /// the layer must have been signed by that key, or the pull fails with
+    /// [`Error::SignerMismatch`].
+    ///
+    /// Returns [`Error::InvalidKey`] if any of the keys isn't an ed25519 key, as embedded
+    /// signatures can't be made with other algorithms
+    pub fn with_trusted_keys(
+        mut self,
+        keys: impl IntoIterator<Item = VerifyingKey>,
+    ) -> Result<Self> {
+        let keys = keys.into_iter().collect::<Vec<_>>();
+        if keys
+            .iter()
+            .any(|key| !matches!(key, VerifyingKey::Ed25519(_)))
+        {
+            return Err(Error::InvalidKey(
+                "embedded signatures require ed25519 keys".into(),
+            ));
         }
+        self.trusted_keys = keys;
+        Ok(self)
+    }
+
+    /// A convenience wrapper around [`Client::pull`] that pulls a wasm component and errors if
+    /// there are layers that aren't wasm. If trusted keys were set with
+    /// [`WasmClient::with_trusted_keys`], the embedded signature of the layer is verified as well
+    pub async fn pull(&self, image: &Reference, auth: &RegistryAuth) -> Result<ImageData> {
+        let image_data = match self.cache.as_ref() {
+            Some(cache) => self.pull_cached(cache, image, auth).await?,
+            None => {
+                let image_data = self
+                    .client
+                    .pull(image, auth, vec![WASM_LAYER_MEDIA_TYPE])
+                    .await?;
+                if image_data.layers.len() != 1 {
+                    return Err(Error::WrongLayerCount(image_data.layers.len()));
+                }
 
+                if image_data.config.media_type != WASM_MANIFEST_CONFIG_MEDIA_TYPE {
+                    return Err(Error::WrongConfigMediaType(image_data.config.media_type));
+                }
+                image_data
+            }
+        };
+
+        if !self.trusted_keys.is_empty() {
+            let config = WasmConfig::try_from(image_data.config.data.as_ref())?;
+            for layer in image_data.layers.iter() {
+                check_signer(
+                    verify_embedded_signature(&layer.data, &self.trusted_keys),
+                    &sha256_digest(&layer.data),
+                    &config,
+                )?;
+            }
+        }
         Ok(image_data)
     }
 
-    /// A convenience wrapper around [`Client::pull_manifest_and_config`] that parses the config as
-    /// a [`WasmConfig`] type
-    pub async fn pull_manifest_and_config(
+    /// Same as [`WasmClient::pull`], but also strictly parses the config as a [`WasmConfig`] (see
+    /// [`WasmConfig::parse_strict`]) and verifies that the digest of the pulled layer matches both
+    /// the layer descriptor in the manifest and the `layerDigests` entry in the config. Returns
+    /// [`Error::LayerDigestMismatch`] if either digest doesn't match and [`Error::MissingManifest`]
+    /// if the pull didn't return the manifest to check against
+    pub async fn pull_and_verify(
         &self,
         image: &Reference,
         auth: &RegistryAuth,
-    ) -> anyhow::Result<(OciImageManifest, WasmConfig, String)> {
-        let (manifest, digest, config) = self.client.pull_manifest_and_config(image, auth).await?;
-        if manifest.layers.len() != 1 {
-            anyhow::bail!("Wasm components must have exactly one layer");
+    ) -> Result<(ImageData, WasmConfig)> {
+        let image_data = self.pull(image, auth).await?;
+        let config = WasmConfig::parse_strict(&image_data.config.data)?;
+
+        let manifest = image_data.manifest.as_ref().ok_or(Error::MissingManifest)?;
+        if manifest.layers.len() != image_data.layers.len() {
+            return Err(Error::WrongLayerCount(manifest.layers.len()));
         }
-        if manifest.media_type.as_deref().unwrap_or_default() != WASM_MANIFEST_MEDIA_TYPE {
-            anyhow::bail!(
-                "Wasm components must have a manifest of type {}",
-                WASM_MANIFEST_MEDIA_TYPE
-            );
+        for (descriptor, layer) in manifest.layers.iter().zip(image_data.layers.iter()) {
+            let actual = sha256_digest(&layer.data);
+            if actual != descriptor.digest {
+                return Err(Error::LayerDigestMismatch {
+                    expected: descriptor.digest.clone(),
+                    actual,
+                });
+            }
         }
+        config.verify_layers(&image_data.layers)?;
 
-        if manifest.config.media_type != WASM_MANIFEST_CONFIG_MEDIA_TYPE {
-            anyhow::bail!(
-                "Wasm components must have a config of type {}",
-                WASM_MANIFEST_CONFIG_MEDIA_TYPE
-            );
-        }
+        Ok((image_data, config))
+    }
 
-        let config = WasmConfig::try_from(config)?;
+    /// A convenience wrapper around [`Client::pull_manifest_and_config`] that parses the config as
+    /// a [`WasmConfig`] type. The config is parsed with [`WasmConfig::parse_strict`], so configs
+    /// violating the OCI Wasm specification are rejected with [`Error::InvalidConfig`]
+    pub async fn pull_manifest_and_config(
+        &self,
+        image: &Reference,
+        auth: &RegistryAuth,
+    ) -> Result<(OciImageManifest, WasmConfig, String)> {
+        if let Some(cache) = self.cache.as_ref() {
+            let (manifest, digest) = self.cached_manifest(cache, image, auth).await?;
+            validate_manifest(&manifest)?;
+            let config = self.cached_blob(cache, image, &manifest.config).await?;
+            let config = WasmConfig::parse_strict(config)?;
+            return Ok((manifest, config, digest));
+        }
+        let (manifest, digest, config) = self.client.pull_manifest_and_config(image, auth).await?;
+        validate_manifest(&manifest)?;
+        let config = WasmConfig::parse_strict(config)?;
         Ok((manifest, config, digest))
     }
 
@@ -108,14 +244,1004 @@ impl WasmClient {
         component_layer: ImageLayer,
         config: impl ToConfig,
         annotations: Option<BTreeMap<String, String>>,
-    ) -> anyhow::Result<PushResponse> {
+    ) -> Result<PushResponse> {
         let layers = vec![component_layer];
         let config = config.to_config()?;
-        let mut manifest = OciImageManifest::build(&layers, &config, annotations);
-        manifest.media_type = Some(WASM_MANIFEST_MEDIA_TYPE.to_string());
+        self.check_required_world(&config)?;
+        let manifest = build_manifest(&layers, &config, annotations);
         self.client
             .push(image, &layers, config, auth, Some(manifest))
             .await
             .map_err(Into::into)
     }
+
+    /// Pushes several wasm artifacts, such as a `wasip1` module and a `wasip2` component build of
+    /// the same application, along with an OCI image index that references all of them. Each
+    /// manifest is pushed by digest and listed in the index with a platform whose architecture is
+    /// [`WASM_ARCHITECTURE`] and whose OS is taken from its config. The index is pushed to the
+    /// given reference. Use [`WasmClient::pull_platform`] to pull the best artifact for a host.
+    ///
+    /// Every config is validated and checked against its layer before anything is pushed. Returns
+    /// the digest of the index
+    pub async fn push_index(
+        &self,
+        image: &Reference,
+        auth: &RegistryAuth,
+        artifacts: Vec<(ImageLayer, WasmConfig)>,
+        annotations: Option<BTreeMap<String, String>>,
+    ) -> Result<String> {
+        let mut prepared = Vec::with_capacity(artifacts.len());
+        for (layer, wasm_config) in artifacts {
+            wasm_config.validate()?;
+            wasm_config.verify_layers(std::slice::from_ref(&layer))?;
+            let config = wasm_config.to_config()?;
+            self.check_required_world(&config)?;
+            prepared.push((layer, config, wasm_config.os));
+        }
+
+        self.client
+            .store_auth_if_needed(image.resolve_registry(), auth)
+            .await;
+        let mut manifests = Vec::with_capacity(prepared.len());
+        for (layer, config, os) in prepared {
+            let manifest = build_manifest(std::slice::from_ref(&layer), &config, None);
+            let manifest_data = serde_json::to_vec(&manifest).map_err(Error::ManifestSerialize)?;
+            let digest = sha256_digest(&manifest_data);
+            self.client
+                .push_blob(image, layer.data, &manifest.layers[0].digest)
+                .await?;
+            self.client
+                .push_blob(image, config.data, &manifest.config.digest)
+                .await?;
+            self.client
+                .push_manifest_raw(
+                    &image.clone_with_digest(digest.clone()),
+                    manifest_data.clone(),
+                    HeaderValue::from_static(WASM_MANIFEST_MEDIA_TYPE),
+                )
+                .await?;
+            manifests.push(ImageIndexEntry {
+                media_type: WASM_MANIFEST_MEDIA_TYPE.to_string(),
+                digest,
+                size: manifest_data.len() as i64,
+                platform: Some(Platform {
+                    architecture: Arch::from(WASM_ARCHITECTURE),
+                    os: Os::from(os.as_str()),
+                    os_version: None,
+                    os_features: None,
+                    variant: None,
+                    features: None,
+                }),
+                annotations: None,
+            });
+        }
+
+        let index = OciImageIndex {
+            schema_version: 2,
+            media_type: Some(OCI_IMAGE_INDEX_MEDIA_TYPE.to_string()),
+            manifests,
+            artifact_type: None,
+            annotations,
+        };
+        let index_data = serde_json::to_vec(&index).map_err(Error::ManifestSerialize)?;
+        let digest = sha256_digest(&index_data);
+        self.client
+            .push_manifest_raw(
+                image,
+                index_data,
+                HeaderValue::from_static(OCI_IMAGE_INDEX_MEDIA_TYPE),
+            )
+            .await?;
+        Ok(digest)
+    }
+
+    /// Pulls the artifact best suited for a host that supports the given OSes, in order of
+    /// preference. If the reference points at an image index, such as one pushed with
+    /// [`WasmClient::push_index`], the first entry whose platform matches [`WASM_ARCHITECTURE`]
+    /// and the most preferred OS is pulled. If it points at a single wasm manifest, that manifest
+    /// is pulled as long as its config has one of the given OSes.
+    ///
+    /// The artifact is verified like [`WasmClient::pull_and_verify`]. Returns
+    /// [`Error::NoMatchingPlatform`] if there is no artifact for any of the given OSes
+    pub async fn pull_platform(
+        &self,
+        image: &Reference,
+        auth: &RegistryAuth,
+        preferred_os: &[WasmOs],
+    ) -> Result<(ImageData, WasmConfig)> {
+        let (raw, digest) = self
+            .client
+            .pull_manifest_raw(
+                image,
+                auth,
+                &[OCI_IMAGE_INDEX_MEDIA_TYPE, WASM_MANIFEST_MEDIA_TYPE],
+            )
+            .await?;
+        let no_match = || Error::NoMatchingPlatform(preferred_os.to_vec());
+        match serde_json::from_slice(&raw).map_err(Error::ManifestParse)? {
+            OciManifest::ImageIndex(index) => {
+                let entry = select_platform(&index.manifests, preferred_os).ok_or_else(no_match)?;
+                self.pull_and_verify(&image.clone_with_digest(entry.digest.clone()), auth)
+                    .await
+            }
+            OciManifest::Image(_) => {
+                let (image_data, config) = self
+                    .pull_and_verify(&image.clone_with_digest(digest), auth)
+                    .await?;
+                if !preferred_os.contains(&config.os) {
+                    return Err(no_match());
+                }
+                Ok((image_data, config))
+            }
+        }
+    }
+
+    /// Resolves a semver requirement such as `^0.2` or `~1.3.1` to a concrete reference by listing
+    /// all tags in the repository of `image` and picking the highest one that matches (see
+    /// [`resolve_tag`]). Any tag or digest set on `image` is ignored. The returned reference can be
+    /// passed to [`WasmClient::pull`] or any of the other pull methods.
+    ///
+    /// Returns [`Error::NoMatchingVersion`] if no tag matches
+    pub async fn resolve_version(
+        &self,
+        image: &Reference,
+        auth: &RegistryAuth,
+        req: &VersionReq,
+        include_prerelease: bool,
+    ) -> Result<Reference> {
+        let mut tags = Vec::new();
+        let mut last: Option<String> = None;
+        loop {
+            let resp = self
+                .client
+                .list_tags(image, auth, Some(TAG_PAGE_SIZE), last.as_deref())
+                .await?;
+            // Registries may return fewer tags than requested even when more are left, so only an
+            // empty page (or one that doesn't move past `last`) marks the end
+            let next = resp.tags.last().cloned();
+            if next.is_none() || next == last {
+                break;
+            }
+            last = next;
+            tags.extend(resp.tags);
+        }
+
+        let tag = resolve_tag(tags.iter().map(String::as_str), req, include_prerelease)
+            .ok_or_else(|| Error::NoMatchingVersion(req.clone()))?;
+        Ok(Reference::with_tag(
+            image.registry().to_string(),
+            image.repository().to_string(),
+            tag.to_string(),
+        ))
+    }
+
+    /// Copies a wasm artifact from `src` to `dst`, which may be on different registries. The
+    /// artifact is validated with [`WasmClient::pull_manifest_and_config`] before anything is
+    /// copied. Blobs are cross-repository mounted when both references are on the same registry and
+    /// streamed otherwise, so the layer is never fully loaded into memory. The manifest is pushed
+    /// exactly as it was pulled, so the digests and annotations of the copy are identical to the
+    /// source. `dst_auth` is always used for the destination, even when both references are on the
+    /// same registry.
+    ///
+    /// Returns the digest of the copied manifest
+    pub async fn copy(
+        &self,
+        src: &Reference,
+        dst: &Reference,
+        src_auth: &RegistryAuth,
+        dst_auth: &RegistryAuth,
+    ) -> Result<String> {
+        let (manifest, _, digest) = self.pull_manifest_and_config(src, src_auth).await?;
+        validate_layer_media_type(&manifest.layers[0].media_type)?;
+        // Pin the source to the digest we just validated so a moving tag can't change what we copy
+        let src = src.clone_with_digest(digest.clone());
+        let (raw_manifest, _) = self
+            .client
+            .pull_manifest_raw(&src, src_auth, &[WASM_MANIFEST_MEDIA_TYPE])
+            .await?;
+        let actual = sha256_digest(&raw_manifest);
+        if actual != digest {
+            return Err(Error::Oci(OciDistributionError::DigestError(
+                DigestError::VerificationError {
+                    expected: digest,
+                    actual,
+                },
+            )));
+        }
+
+        // The client stores credentials per registry, so on a copy within the same registry the
+        // credentials stored while pulling the source would otherwise be used for the destination
+        for op in [RegistryOperation::Pull, RegistryOperation::Push] {
+            self.client.auth(dst, dst_auth, op).await?;
+        }
+        for descriptor in manifest.layers.iter().chain([&manifest.config]) {
+            copy_blob(&self.client, &src, dst, descriptor).await?;
+        }
+        self.client
+            .push_manifest_raw(
+                dst,
+                raw_manifest,
+                HeaderValue::from_static(WASM_MANIFEST_MEDIA_TYPE),
+            )
+            .await?;
+
+        Ok(digest)
+    }
+
+    /// Attaches an artifact, such as WIT documentation, an SBOM or a test report, to the wasm
+    /// manifest `subject` points at. The blobs are pushed along with a manifest whose `subject`
+    /// field refers to the exact digest of the wasm manifest and whose `artifactType` is set to
+    /// `artifact_type`. The manifest is pushed by digest to the same repository as the subject.
+    ///
+    /// On registries that don't support the OCI 1.1 referrers API, the artifact is also added to
+    /// the index stored under the `sha256-<digest>` fallback tag so [`WasmClient::referrers`] can
+    /// find it. Returns the digest of the pushed manifest
+    pub async fn attach(
+        &self,
+        subject: &Reference,
+        auth: &RegistryAuth,
+        artifact_type: &str,
+        blobs: Vec<ImageLayer>,
+        annotations: Option<BTreeMap<String, String>>,
+    ) -> Result<String> {
+        let (subject, subject_descriptor) = self.resolve_subject(subject, auth).await?;
+
+        let empty = OciDescriptor {
+            media_type: EMPTY_MEDIA_TYPE.to_string(),
+            digest: sha256_digest(EMPTY_DATA),
+            size: EMPTY_DATA.len() as i64,
+            ..Default::default()
+        };
+        self.client
+            .push_blob(&subject, EMPTY_DATA.to_vec(), &empty.digest)
+            .await?;
+        let mut layers = Vec::with_capacity(blobs.len());
+        for blob in blobs {
+            let descriptor = OciDescriptor {
+                media_type: blob.media_type.clone(),
+                digest: blob.sha256_digest(),
+                size: blob.data.len() as i64,
+                annotations: blob.annotations.clone(),
+                ..Default::default()
+            };
+            self.client
+                .push_blob(&subject, blob.data, &descriptor.digest)
+                .await?;
+            layers.push(descriptor);
+        }
+        if layers.is_empty() {
+            layers.push(empty.clone());
+        }
+
+        let manifest = OciImageManifest {
+            schema_version: 2,
+            media_type: Some(WASM_MANIFEST_MEDIA_TYPE.to_string()),
+            config: empty,
+            layers,
+            subject: Some(subject_descriptor.clone()),
+            artifact_type: Some(artifact_type.to_string()),
+            annotations: annotations.clone(),
+        };
+        let manifest_data = serde_json::to_vec(&manifest).map_err(Error::ManifestSerialize)?;
+        let digest = sha256_digest(&manifest_data);
+        self.client
+            .push_manifest_raw(
+                &subject.clone_with_digest(digest.clone()),
+                manifest_data.clone(),
+                HeaderValue::from_static(WASM_MANIFEST_MEDIA_TYPE),
+            )
+            .await?;
+
+        match self.client.pull_referrers(&subject, None).await {
+            Ok(_) => return Ok(digest),
+            Err(e) if !is_not_found(&e) => return Err(e.into()),
+            Err(_) => {}
+        }
+        let tag_ref = Reference::with_tag(
+            subject.registry().to_string(),
+            subject.repository().to_string(),
+            fallback_tag(&subject_descriptor.digest),
+        );
+        let mut index = self.fallback_index(&tag_ref, auth).await?;
+        index.manifests.retain(|entry| entry.digest != digest);
+        index.manifests.push(ReferrerDescriptor {
+            media_type: WASM_MANIFEST_MEDIA_TYPE.to_string(),
+            digest: digest.clone(),
+            size: manifest_data.len() as i64,
+            artifact_type: Some(artifact_type.to_string()),
+            annotations,
+        });
+        let index_data = serde_json::to_vec(&index).map_err(Error::ManifestSerialize)?;
+        self.client
+            .push_manifest_raw(
+                &tag_ref,
+                index_data,
+                HeaderValue::from_static(OCI_IMAGE_INDEX_MEDIA_TYPE),
+            )
+            .await?;
+        Ok(digest)
+    }
+
+    /// Lists the artifacts attached to the wasm manifest `subject` points at, such as those
+    /// attached with [`WasmClient::attach`]. If `artifact_type` is set, only referrers with that
+    /// artifact type are returned.
+    ///
+    /// This uses the OCI 1.1 referrers API, falling back to the index stored under the
+    /// `sha256-<digest>` tag on registries that don't support it. The manifest of every referrer
+    /// is pulled and verified so its artifact type and blobs can be returned
+    pub async fn referrers(
+        &self,
+        subject: &Reference,
+        auth: &RegistryAuth,
+        artifact_type: Option<&str>,
+    ) -> Result<Vec<Referrer>> {
+        let (subject, subject_descriptor) = self.resolve_subject(subject, auth).await?;
+        let digests = match self.client.pull_referrers(&subject, artifact_type).await {
+            Ok(index) => index
+                .manifests
+                .into_iter()
+                .map(|entry| entry.digest)
+                .collect::<Vec<_>>(),
+            Err(e) if !is_not_found(&e) => return Err(e.into()),
+            Err(_) => {
+                let tag_ref = Reference::with_tag(
+                    subject.registry().to_string(),
+                    subject.repository().to_string(),
+                    fallback_tag(&subject_descriptor.digest),
+                );
+                self.fallback_index(&tag_ref, auth)
+                    .await?
+                    .manifests
+                    .into_iter()
+                    .map(|entry| entry.digest)
+                    .collect()
+            }
+        };
+
+        let mut referrers = Vec::with_capacity(digests.len());
+        for digest in digests {
+            let (raw, _) = self
+                .client
+                .pull_manifest_raw(
+                    &subject.clone_with_digest(digest.clone()),
+                    auth,
+                    &[WASM_MANIFEST_MEDIA_TYPE],
+                )
+                .await?;
+            let actual = sha256_digest(&raw);
+            if actual != digest {
+                return Err(Error::Oci(OciDistributionError::DigestError(
+                    DigestError::VerificationError {
+                        expected: digest,
+                        actual,
+                    },
+                )));
+            }
+            let manifest: OciImageManifest =
+                serde_json::from_slice(&raw).map_err(Error::ManifestParse)?;
+            if manifest.subject.as_ref().map(|s| s.digest.as_str())
+                != Some(subject_descriptor.digest.as_str())
+            {
+                continue;
+            }
+            let referrer_type = manifest
+                .artifact_type
+                .clone()
+                .unwrap_or_else(|| manifest.config.media_type.clone());
+            if artifact_type.is_some_and(|wanted| wanted != referrer_type) {
+                continue;
+            }
+            referrers.push(Referrer {
+                digest,
+                artifact_type: referrer_type,
+                manifest,
+            });
+        }
+        Ok(referrers)
+    }
+
+    /// Signs the wasm manifest `image` points at with the given key and pushes a cosign compatible
+    /// signature next to it, under the `sha256-<digest>.sig` tag of the same repository. Signatures
+    /// already stored under that tag are kept, so an artifact can be signed with several keys.
+    /// The signature can be verified with [`WasmClient::pull_verified`] or `cosign verify`.
+    /// Returns the digest of the pushed signature manifest
+    pub async fn sign(
+        &self,
+        image: &Reference,
+        auth: &RegistryAuth,
+        key: &SigningKey,
+    ) -> Result<String> {
+        let (subject, descriptor) = self.resolve_subject(image, auth).await?;
+        let payload = SimpleSigning::new(
+            format!("{}/{}", subject.registry(), subject.repository()),
+            descriptor.digest.clone(),
+        );
+        let payload = serde_json::to_vec(&payload).map_err(Error::ManifestSerialize)?;
+        let signature = encode_signature(&key.sign(&payload));
+        let layer = OciDescriptor {
+            media_type: SIMPLE_SIGNING_MEDIA_TYPE.to_string(),
+            digest: sha256_digest(&payload),
+            size: payload.len() as i64,
+            annotations: Some(BTreeMap::from([(
+                SIGNATURE_ANNOTATION.to_string(),
+                signature,
+            )])),
+            ..Default::default()
+        };
+
+        let tag_ref = Reference::with_tag(
+            subject.registry().to_string(),
+            subject.repository().to_string(),
+            signature_tag(&descriptor.digest),
+        );
+        let mut layers = self
+            .signature_manifest(&tag_ref, auth)
+            .await?
+            .map(|manifest| manifest.layers)
+            .unwrap_or_default();
+        if !layers
+            .iter()
+            .any(|l| l.digest == layer.digest && l.annotations == layer.annotations)
+        {
+            self.client
+                .push_blob(&tag_ref, payload, &layer.digest)
+                .await?;
+            layers.push(layer);
+        }
+
+        let config = serde_json::to_vec(&serde_json::json!({
+            "architecture": "",
+            "os": "",
+            "config": {},
+            "rootfs": {
+                "type": "layers",
+                "diff_ids": layers.iter().map(|l| l.digest.as_str()).collect::<Vec<_>>(),
+            },
+        }))
+        .map_err(Error::ManifestSerialize)?;
+        let config_descriptor = OciDescriptor {
+            media_type: SIGNATURE_CONFIG_MEDIA_TYPE.to_string(),
+            digest: sha256_digest(&config),
+            size: config.len() as i64,
+            ..Default::default()
+        };
+        self.client
+            .push_blob(&tag_ref, config, &config_descriptor.digest)
+            .await?;
+
+        let manifest = OciImageManifest {
+            schema_version: 2,
+            media_type: Some(WASM_MANIFEST_MEDIA_TYPE.to_string()),
+            config: config_descriptor,
+            layers,
+            ..Default::default()
+        };
+        let manifest_data = serde_json::to_vec(&manifest).map_err(Error::ManifestSerialize)?;
+        let digest = sha256_digest(&manifest_data);
+        self.client
+            .push_manifest_raw(
+                &tag_ref,
+                manifest_data,
+                HeaderValue::from_static(WASM_MANIFEST_MEDIA_TYPE),
+            )
+            .await?;
+        Ok(digest)
+    }
+
+    /// Same as [`WasmClient::pull_and_verify`], but first fetches the cosign signatures of the
+    /// manifest and only pulls if at least one of them was made by one of the given keys. The
+    /// verified manifest is pulled by digest, so a tag moved after verification has no effect.
+    /// Returns [`Error::NoValidSignature`] if the manifest isn't signed by any of the keys, or
+    /// [`Error::SignaturePayloadTooLarge`] if the registry sends an oversized signature payload
+    pub async fn pull_verified(
+        &self,
+        image: &Reference,
+        auth: &RegistryAuth,
+        keys: &[VerifyingKey],
+    ) -> Result<(ImageData, WasmConfig)> {
+        let (subject, descriptor) = self.resolve_subject(image, auth).await?;
+        let tag_ref = Reference::with_tag(
+            subject.registry().to_string(),
+            subject.repository().to_string(),
+            signature_tag(&descriptor.digest),
+        );
+        let manifest = self
+            .signature_manifest(&tag_ref, auth)
+            .await?
+            .ok_or_else(|| Error::NoValidSignature(descriptor.digest.clone()))?;
+
+        for layer in manifest.layers {
+            if layer.media_type != SIMPLE_SIGNING_MEDIA_TYPE
+                || usize::try_from(layer.size).map_or(true, |size| size > MAX_SIMPLE_SIGNING_SIZE)
+            {
+                continue;
+            }
+            let Some(signature) = layer
+                .annotations
+                .as_ref()
+                .and_then(|a| a.get(SIGNATURE_ANNOTATION))
+                .and_then(|s| decode_signature(s))
+            else {
+                continue;
+            };
+            let payload = self.pull_signature_payload(&tag_ref, &layer).await?;
+            let signs_subject = serde_json::from_slice::<SimpleSigning>(&payload)
+                .is_ok_and(|p| p.signs(&descriptor.digest));
+            if signs_subject && keys.iter().any(|key| key.verify(&payload, &signature)) {
+                return self.pull_and_verify(&subject, auth).await;
+            }
+        }
+        Err(Error::NoValidSignature(descriptor.digest))
+    }
+
+    /// Same as [`WasmClient::pull`], but streams the wasm layer into the given writer instead of
+    /// buffering it in memory. The layer digest is verified incrementally as it is written and the
+    /// layer descriptor is checked against the `layerDigests` entry in the config before any bytes
+    /// are pulled. Returns the manifest, config and manifest digest just like
+    /// [`WasmClient::pull_manifest_and_config`]
+    ///
+    /// If trusted keys were set with [`WasmClient::with_trusted_keys`], the embedded signature can
+    /// only be verified once the whole layer has been pulled, so the layer is staged in a
+    /// temporary file and only written to the writer after it passed verification.
+    ///
+    /// Please note that if this returns an error, some bytes may have already been written to the
+    /// writer
+    pub async fn pull_to_writer<W: AsyncWrite + Unpin>(
+        &self,
+        image: &Reference,
+        auth: &RegistryAuth,
+        mut writer: W,
+    ) -> Result<(OciImageManifest, WasmConfig, String)> {
+        if self.trusted_keys.is_empty() {
+            return self.stream_layer(image, auth, writer).await;
+        }
+        let (mut file, tmp) = create_tmp_file(&std::env::temp_dir().join("oci-wasm-layer")).await?;
+        let res: Result<_> = async {
+            let res = self.pull_to_tmp(image, auth, &mut file).await?;
+            file.rewind().await?;
+            tokio::io::copy(&mut file, &mut writer).await?;
+            writer.flush().await?;
+            Ok(res)
+        }
+        .await;
+        let _ = tokio::fs::remove_file(&tmp).await;
+        res
+    }
+
+    /// Same as [`WasmClient::pull_to_writer`], but writes the wasm layer to a file at the given
+    /// path. The layer is streamed into a temporary file next to it, which is only moved into
+    /// place once the layer has been fully pulled and, if trusted keys were set with
+    /// [`WasmClient::with_trusted_keys`], its embedded signature was verified. If the pull fails,
+    /// any existing file at the path is left untouched
+    pub async fn pull_to_file(
+        &self,
+        image: &Reference,
+        auth: &RegistryAuth,
+        path: impl AsRef<Path>,
+    ) -> Result<(OciImageManifest, WasmConfig, String)> {
+        let path = path.as_ref();
+        let (mut file, tmp) = create_tmp_file(path).await?;
+        let res = self.pull_to_tmp(image, auth, &mut file).await;
+        drop(file);
+        let res = match res {
+            Ok(res) => tokio::fs::rename(&tmp, path)
+                .await
+                .map(|_| res)
+                .map_err(Error::from),
+            Err(e) => Err(e),
+        };
+        if res.is_err() {
+            let _ = tokio::fs::remove_file(&tmp).await;
+        }
+        res
+    }
+
+    /// Pulls a wasm artifact and writes it into an OCI image layout directory, like
+    /// [`write_image_layout`](crate::write_image_layout). Unlike pulling first and writing the
+    /// result, the manifest is written byte for byte as the registry returned it, so the artifact
+    /// keeps its digest even if it was pushed by a tool that doesn't use canonical JSON. Returns
+    /// the digest of the manifest
+    pub async fn pull_to_layout(
+        &self,
+        image: &Reference,
+        auth: &RegistryAuth,
+        dir: impl AsRef<Path>,
+        ref_name: Option<&str>,
+    ) -> Result<String> {
+        let (raw, digest) = self
+            .client
+            .pull_manifest_raw(image, auth, &[WASM_MANIFEST_MEDIA_TYPE])
+            .await?;
+        let image_data = self.pull(&image.clone_with_digest(digest), auth).await?;
+        write_layout(dir.as_ref(), &image_data, Some(raw.to_vec()), ref_name).await
+    }
+
+    /// Same as [`WasmClient::push`], but streams the wasm layer from the file at the given path
+    /// instead of requiring it to be loaded into memory. The file is read once to compute its
+    /// digest and once more while it is being uploaded.
+    ///
+    /// If the given config is a [`WasmConfig`], its `layerDigests` must match the digest of the
+    /// file or [`Error::LayerDigestMismatch`] is returned before anything is pushed
+    pub async fn push_from_file(
+        &self,
+        image: &Reference,
+        auth: &RegistryAuth,
+        path: impl AsRef<Path>,
+        config: impl ToConfig,
+        annotations: Option<BTreeMap<String, String>>,
+    ) -> Result<PushResponse> {
+        let path = path.as_ref();
+        let (layer_digest, layer_size) = sha256_digest_file(path).await?;
+        let config = config.to_config()?;
+        self.check_required_world(&config)?;
+        if let Ok(wasm_config) = WasmConfig::try_from(config.data.as_ref()) {
+            wasm_config.verify_layer_digest(&layer_digest)?;
+        }
+
+        let mut manifest = build_manifest(&[], &config, annotations);
+        manifest.layers.push(OciDescriptor {
+            media_type: WASM_LAYER_MEDIA_TYPE.to_string(),
+            digest: layer_digest.clone(),
+            size: layer_size as i64,
+            ..Default::default()
+        });
+
+        self.client
+            .store_auth_if_needed(image.resolve_registry(), auth)
+            .await;
+        let file = tokio::fs::File::open(path).await?;
+        self.client
+            .push_blob_stream(image, file_stream(file), &layer_digest)
+            .await?;
+        let config_url = self
+            .client
+            .push_blob(image, config.data, &manifest.config.digest)
+            .await?;
+        let manifest_url = self.client.push_manifest(image, &manifest.into()).await?;
+
+        Ok(PushResponse {
+            config_url,
+            manifest_url,
+        })
+    }
+
+    /// Checks the component described by the config against the world required by this client, if
+    /// any
+    fn check_required_world(&self, config: &Config) -> Result<()> {
+        let Some((resolve, world_id)) = &self.required_world else {
+            return Ok(());
+        };
+        let wasm_config = WasmConfig::try_from(config.data.as_ref())?;
+        let component = wasm_config.component.unwrap_or_default();
+        let report = component.satisfies_world(resolve, *world_id)?;
+        if !report.is_conformant() {
+            return Err(Error::WorldConformance(report));
+        }
+        Ok(())
+    }
+
+    /// Streams the wasm layer into the given writer without checking its embedded signature
+    async fn stream_layer<W: AsyncWrite + Unpin>(
+        &self,
+        image: &Reference,
+        auth: &RegistryAuth,
+        mut writer: W,
+    ) -> Result<(OciImageManifest, WasmConfig, String)> {
+        let (manifest, config, digest) = self.pull_manifest_and_config(image, auth).await?;
+        let layer = &manifest.layers[0];
+        validate_layer_media_type(&layer.media_type)?;
+        config.verify_layer_digest(&layer.digest)?;
+
+        self.client.pull_blob(image, layer, &mut writer).await?;
+        writer.flush().await?;
+
+        Ok((manifest, config, digest))
+    }
+
+    /// Streams the wasm layer into the given empty file and checks its embedded signature if
+    /// trusted keys were set
+    async fn pull_to_tmp(
+        &self,
+        image: &Reference,
+        auth: &RegistryAuth,
+        file: &mut tokio::fs::File,
+    ) -> Result<(OciImageManifest, WasmConfig, String)> {
+        let res = self.stream_layer(image, auth, &mut *file).await?;
+        if self.trusted_keys.is_empty() {
+            return Ok(res);
+        }
+        file.rewind().await?;
+        let verified = verify_embedded_signature_stream(&mut *file, &self.trusted_keys).await?;
+        check_signer(verified, &res.0.layers[0].digest, &res.1)?;
+        Ok(res)
+    }
+
+    /// Resolves the given reference to a wasm manifest, returning a reference pinned to its digest
+    /// and a descriptor for it
+    async fn resolve_subject(
+        &self,
+        subject: &Reference,
+        auth: &RegistryAuth,
+    ) -> Result<(Reference, OciDescriptor)> {
+        let (raw, digest) = self
+            .client
+            .pull_manifest_raw(subject, auth, &[WASM_MANIFEST_MEDIA_TYPE])
+            .await?;
+        let manifest: OciImageManifest =
+            serde_json::from_slice(&raw).map_err(Error::ManifestParse)?;
+        validate_manifest(&manifest)?;
+        let descriptor = OciDescriptor {
+            media_type: WASM_MANIFEST_MEDIA_TYPE.to_string(),
+            digest: digest.clone(),
+            size: raw.len() as i64,
+            ..Default::default()
+        };
+        Ok((subject.clone_with_digest(digest), descriptor))
+    }
+
+    /// Pulls the referrers index stored under the given fallback tag, returning an empty index if
+    /// it doesn't exist yet
+    async fn fallback_index(
+        &self,
+        tag_ref: &Reference,
+        auth: &RegistryAuth,
+    ) -> Result<ReferrersIndex> {
+        match self
+            .client
+            .pull_manifest_raw(tag_ref, auth, &[OCI_IMAGE_INDEX_MEDIA_TYPE])
+            .await
+        {
+            Ok((raw, _)) => serde_json::from_slice(&raw).map_err(Error::ManifestParse),
+            Err(e) if is_not_found(&e) => Ok(ReferrersIndex::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Pulls the cosign signature manifest stored under the given tag, returning `None` if there
+    /// isn't one
+    async fn signature_manifest(
+        &self,
+        tag_ref: &Reference,
+        auth: &RegistryAuth,
+    ) -> Result<Option<OciImageManifest>> {
+        match self
+            .client
+            .pull_manifest_raw(tag_ref, auth, &[WASM_MANIFEST_MEDIA_TYPE])
+            .await
+        {
+            Ok((raw, _)) => serde_json::from_slice(&raw)
+                .map(Some)
+                .map_err(Error::ManifestParse),
+            Err(e) if is_not_found(&e) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Pulls the simple signing payload of a signature layer, failing with
+    /// [`Error::SignaturePayloadTooLarge`] as soon as more bytes than a payload can reasonably
+    /// have are received, no matter what size the layer descriptor claims
+    async fn pull_signature_payload(
+        &self,
+        tag_ref: &Reference,
+        layer: &OciDescriptor,
+    ) -> Result<Vec<u8>> {
+        let mut stream = self.client.pull_blob_stream(tag_ref, layer).await?;
+        let mut payload = Vec::new();
+        while let Some(chunk) = stream.try_next().await? {
+            if payload.len() + chunk.len() > MAX_SIMPLE_SIGNING_SIZE {
+                return Err(Error::SignaturePayloadTooLarge(layer.digest.clone()));
+            }
+            payload.extend_from_slice(&chunk);
+        }
+        Ok(payload)
+    }
+
+    /// The cached equivalent of [`WasmClient::pull`]
+    async fn pull_cached(
+        &self,
+        cache: &Cache,
+        image: &Reference,
+        auth: &RegistryAuth,
+    ) -> Result<ImageData> {
+        let (manifest, digest) = self.cached_manifest(cache, image, auth).await?;
+        for layer in manifest.layers.iter() {
+            validate_layer_media_type(&layer.media_type)?;
+        }
+        if manifest.layers.len() != 1 {
+            return Err(Error::WrongLayerCount(manifest.layers.len()));
+        }
+        if manifest.config.media_type != WASM_MANIFEST_CONFIG_MEDIA_TYPE {
+            return Err(Error::WrongConfigMediaType(manifest.config.media_type));
+        }
+
+        let config = self.cached_blob(cache, image, &manifest.config).await?;
+        let layer = self.cached_blob(cache, image, &manifest.layers[0]).await?;
+        Ok(ImageData {
+            layers: vec![ImageLayer::new(
+                layer,
+                manifest.layers[0].media_type.clone(),
+                manifest.layers[0].annotations.clone(),
+            )],
+            digest: Some(digest),
+            config: Config::new(
+                config,
+                manifest.config.media_type.clone(),
+                manifest.annotations.clone(),
+            ),
+            manifest: Some(manifest),
+        })
+    }
+
+    /// Returns the manifest and its digest for the given reference, using the cache if the
+    /// reference was resolved recently enough
+    async fn cached_manifest(
+        &self,
+        cache: &Cache,
+        image: &Reference,
+        auth: &RegistryAuth,
+    ) -> Result<(OciImageManifest, String)> {
+        self.client
+            .store_auth_if_needed(image.resolve_registry(), auth)
+            .await;
+        if let Some(digest) = cache.get_ref(image).await? {
+            if let Some(manifest) = cache.get_manifest(&digest).await? {
+                return Ok((manifest, digest));
+            }
+        }
+        let (mut raw, mut digest) = self
+            .client
+            .pull_manifest_raw(
+                image,
+                auth,
+                &[WASM_MANIFEST_MEDIA_TYPE, OCI_IMAGE_INDEX_MEDIA_TYPE],
+            )
+            .await?;
+        // Let the client pick the platform from an index, then pull the selected manifest raw
+        if !matches!(serde_json::from_slice(&raw), Ok(OciManifest::Image(_))) {
+            let (_, selected) = self.client.pull_image_manifest(image, auth).await?;
+            (raw, digest) = self
+                .client
+                .pull_manifest_raw(
+                    &image.clone_with_digest(selected),
+                    auth,
+                    &[WASM_MANIFEST_MEDIA_TYPE],
+                )
+                .await?;
+        }
+        let manifest: OciImageManifest =
+            serde_json::from_slice(&raw).map_err(Error::ManifestParse)?;
+        cache.put_manifest(&digest, &raw).await?;
+        cache.put_ref(image, &digest).await?;
+        Ok((manifest, digest))
+    }
+
+    /// Returns the blob for the given descriptor from the cache, pulling and caching it if it
+    /// isn't there
+    async fn cached_blob(
+        &self,
+        cache: &Cache,
+        image: &Reference,
+        descriptor: &OciDescriptor,
+    ) -> Result<Vec<u8>> {
+        if let Some(data) = cache.get_blob(&descriptor.digest).await? {
+            return Ok(data);
+        }
+        let mut data = Vec::new();
+        self.client.pull_blob(image, descriptor, &mut data).await?;
+        cache.put_blob(&descriptor.digest, &data).await?;
+        Ok(data)
+    }
+}
+
+/// Copies a single blob from `src` to `dst`. Blobs that already exist in `dst` are skipped. If both
+/// references are on the same registry, a cross-repository mount is attempted first, falling back
+/// to streaming the blob if the registry refuses the mount
+async fn copy_blob(
+    client: &Client,
+    src: &Reference,
+    dst: &Reference,
+    descriptor: &OciDescriptor,
+) -> Result<()> {
+    if client.blob_exists(dst, &descriptor.digest).await? {
+        return Ok(());
+    }
+    if src.resolve_registry() == dst.resolve_registry()
+        && client
+            .mount_blob(dst, src, &descriptor.digest)
+            .await
+            .is_ok()
+    {
+        return Ok(());
+    }
+    let stream = client
+        .pull_blob_stream(src, descriptor)
+        .await?
+        .map_err(OciDistributionError::from);
+    client
+        .push_blob_stream(dst, stream, &descriptor.digest)
+        .await?;
+    Ok(())
+}
+
+/// Checks the result of verifying the embedded signature of the layer with the given digest: it
+/// must have been made by one of the trusted keys and, if the config records a `signerKeyId`, by
+/// that key in particular
+fn check_signer(verified: Option<String>, layer_digest: &str, config: &WasmConfig) -> Result<()> {
+    let key_id = verified.ok_or_else(|| Error::NoValidSignature(layer_digest.to_string()))?;
+    match config.signer_key_id.as_deref() {
+        Some(expected) if expected != key_id => Err(Error::SignerMismatch {
+            expected: expected.to_string(),
+            actual: key_id,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Builds a wasm manifest for the given layers and config
+pub(crate) fn build_manifest(
+    layers: &[ImageLayer],
+    config: &Config,
+    annotations: Option<BTreeMap<String, String>>,
+) -> OciImageManifest {
+    let mut manifest = OciImageManifest::build(layers, config, annotations);
+    manifest.media_type = Some(WASM_MANIFEST_MEDIA_TYPE.to_string());
+    manifest
+}
+
+/// Serializes the value as canonical JSON, the same way [`Client::push_manifest`] serializes
+/// manifests, so digests computed from the result match the ones in the registry
+pub(crate) fn canonical_json(value: &impl serde::Serialize) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    let mut ser =
+        serde_json::Serializer::with_formatter(&mut data, olpc_cjson::CanonicalFormatter::new());
+    value
+        .serialize(&mut ser)
+        .map_err(Error::ManifestSerialize)?;
+    Ok(data)
+}
+
+/// Validates that the manifest describes a wasm artifact, using the same rules as
+/// [`WasmClient::pull_manifest_and_config`]
+pub(crate) fn validate_manifest(manifest: &OciImageManifest) -> Result<()> {
+    if manifest.layers.len() != 1 {
+        return Err(Error::WrongLayerCount(manifest.layers.len()));
+    }
+    if manifest.media_type.as_deref().unwrap_or_default() != WASM_MANIFEST_MEDIA_TYPE {
+        return Err(Error::WrongManifestMediaType(
+            manifest.media_type.clone().unwrap_or_default(),
+        ));
+    }
+
+    if manifest.config.media_type != WASM_MANIFEST_CONFIG_MEDIA_TYPE {
+        return Err(Error::WrongConfigMediaType(
+            manifest.config.media_type.clone(),
+        ));
+    }
+    Ok(())
+}
+
+/// Validates that a layer has the `application/wasm` media type, returning the same error as
+/// [`Client::pull`] if it doesn't
+pub(crate) fn validate_layer_media_type(media_type: &str) -> Result<()> {
+    if media_type != WASM_LAYER_MEDIA_TYPE {
+        return Err(Error::Oci(
+            OciDistributionError::IncompatibleLayerMediaTypeError(media_type.to_string()),
+        ));
+    }
+    Ok(())
+}
+
+/// Turns the given file into a stream of chunks suitable for [`Client::push_blob_stream`]
+fn file_stream(
+    file: tokio::fs::File,
+) -> impl Stream<Item = oci_client::errors::Result<bytes::Bytes>> {
+    futures_util::stream::try_unfold(file, |mut file| async move {
+        let mut buf = BytesMut::with_capacity(STREAM_CHUNK_SIZE);
+        let n = file.read_buf(&mut buf).await?;
+        if n == 0 {
+            Ok::<_, OciDistributionError>(None)
+        } else {
+            Ok(Some((buf.freeze(), file)))
+        }
+    })
 }
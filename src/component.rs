@@ -1,100 +1,422 @@
-use std::{collections::HashSet, path::Path};
+use std::{cmp::Reverse, collections::BTreeSet, fmt, path::Path};
 
-use anyhow::Context;
+use semver::Version;
 use serde::{Deserialize, Serialize};
-use wit_parser::{PackageId, Resolve, WorldId};
+use wit_parser::{PackageId, Resolve, World, WorldId, WorldItem, WorldKey};
+
+use crate::{
+    host::{satisfies_interface, split_version},
+    ComponentMetadata, Error, Result, WasmOs,
+};
+
+/// The namespace used by all WASI interfaces
+const WASI_NAMESPACE: &str = "wasi";
+/// The magic bytes every wasm binary starts with
+const WASM_MAGIC: &[u8] = b"\0asm";
+/// The version and layer fields of the header of a core wasm module
+const MODULE_VERSION: &[u8] = &[0x01, 0x00, 0x00, 0x00];
+/// The layer field of the header of a component, which follows a version field that changes
+/// between pre-release versions of the component model
+const COMPONENT_LAYER: &[u8] = &[0x01, 0x00];
+
+/// The kind of wasm binary detected by [`WasmConfig::from_wasm`](crate::WasmConfig::from_wasm)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WasmKind {
+    /// A core wasm module
+    Module,
+    /// A component
+    Component,
+    /// A WIT package encoded as a component
+    WitPackage,
+}
+
+impl WasmKind {
+    /// Returns true if this kind is encoded as a component, which means the config must contain
+    /// component information
+    pub fn is_component(&self) -> bool {
+        matches!(self, WasmKind::Component | WasmKind::WitPackage)
+    }
+}
+
+/// The range of WASI versions referenced by a component's imports and exports, as returned by
+/// [`Component::wasi_versions`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WasiVersionRange {
+    /// The lowest WASI version referenced
+    pub min: Version,
+    /// The highest WASI version referenced
+    pub max: Version,
+}
+
+impl WasiVersionRange {
+    /// Returns the OS a component referencing this range of WASI versions targets. Hosts for a
+    /// newer WASI version also provide the older interfaces, so this is based on the highest
+    /// version referenced
+    pub fn os(&self) -> WasmOs {
+        if self.max.major == 0 && self.max.minor < 3 {
+            WasmOs::Wasip2
+        } else {
+            WasmOs::Wasip3
+        }
+    }
+}
+
+/// The result of checking a component against a world with [`Component::satisfies_world`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorldConformance {
+    /// The fully qualified name of the world the component was checked against
+    pub world: String,
+    /// Exports required by the world that the component doesn't provide
+    pub missing_exports: Vec<String>,
+    /// Imports needed by the component that the world doesn't provide
+    pub disallowed_imports: Vec<String>,
+}
+
+impl WorldConformance {
+    /// Returns true if the component can be used as an implementation of the world
+    pub fn is_conformant(&self) -> bool {
+        self.missing_exports.is_empty() && self.disallowed_imports.is_empty()
+    }
+}
+
+impl fmt::Display for WorldConformance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (missing exports: [{}], disallowed imports: [{}])",
+            self.world,
+            self.missing_exports.join(", "),
+            self.disallowed_imports.join(", ")
+        )
+    }
+}
 
 /// Information about the component in the manifest. This is generally synthesized from a
-/// component's world
-#[derive(Serialize, Deserialize, Debug)]
+/// component's world. Exports and imports are always sorted so the same component always produces
+/// the same config
+#[derive(Serialize, Deserialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Component {
-    /// A list of all exports from the component
+    /// A list of all interfaces exported by the component
     pub exports: Vec<String>,
-    /// A list of all imports from the component
+    /// A list of all interfaces imported by the component
     pub imports: Vec<String>,
+    /// A list of all functions exported directly by the component's world rather than through an
+    /// interface. This isn't part of the OCI Wasm specification and is omitted when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exported_functions: Vec<String>,
+    /// A list of all functions imported directly by the component's world rather than through an
+    /// interface. This isn't part of the OCI Wasm specification and is omitted when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub imported_functions: Vec<String>,
+    /// A list of all types exported directly by the component's world. This isn't part of the OCI
+    /// Wasm specification and is omitted when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exported_types: Vec<String>,
+    /// A list of all types imported directly by the component's world, either defined in the
+    /// world or pulled in from an interface with `use`. This isn't part of the OCI Wasm
+    /// specification and is omitted when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub imported_types: Vec<String>,
+    /// The fully qualified name of the world this component implements. A compiled component
+    /// only carries the interfaces it uses and not the worlds they come from, so this is only
+    /// inferred when a WIT package to match against is given, e.g. with
+    /// [`Component::detect_target`] or
+    /// [`WasmConfig::from_component_with_target`](crate::WasmConfig::from_component_with_target)
     // This is optional metadata for indexing. Implementations MAY use this information to fetch
     // other data to inspect the specified world
     pub target: Option<String>,
+    /// Detailed metadata about the functions and resources in each import and export. This isn't
+    /// part of the OCI Wasm specification and is only set when requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<ComponentMetadata>,
 }
 
 impl Component {
     /// Create a component from a parsed [`Resolve`] and [`WorldId`]. This is a lower level function
-    /// for when you've already parsed a resolve and have the world ID
+    /// for when you've already parsed a resolve and have the world ID. If any other world in the
+    /// resolve is implemented by this one, `target` is set to its fully qualified name (see
+    /// [`Component::detect_target`])
     ///
     /// Returns an error only if the world doesn't exist in the resolve.
-    pub fn from_world(resolve: &Resolve, world_id: WorldId) -> anyhow::Result<Self> {
+    pub fn from_world(resolve: &Resolve, world_id: WorldId) -> Result<Self> {
         let world = resolve
             .worlds
             .iter()
             .find_map(|(id, w)| (id == world_id).then_some(w))
-            .context("component world not found")?;
-        Ok(Component {
-            exports: world
-                .exports
-                .keys()
-                .map(|key| resolve.name_world_key(key))
-                .collect(),
-            imports: world
-                .imports
-                .keys()
-                .map(|key| resolve.name_world_key(key))
-                .collect(),
+            .ok_or(Error::WorldNotFound)?;
+        let exports = WorldItemNames::new(resolve, &world.exports);
+        let imports = WorldItemNames::new(resolve, &world.imports);
+        let mut component = Component {
+            exports: exports.interfaces.into_iter().collect(),
+            imports: imports.interfaces.into_iter().collect(),
+            exported_functions: exports.functions.into_iter().collect(),
+            imported_functions: imports.functions.into_iter().collect(),
+            exported_types: exports.types.into_iter().collect(),
+            imported_types: imports.types.into_iter().collect(),
             target: None,
-        })
+            metadata: None,
+        };
+        let candidates = resolve.worlds.iter().map(|(id, _)| id);
+        component.target = component.find_target(resolve, candidates.filter(|id| *id != world_id));
+        Ok(component)
     }
 
     /// Create a component from a parsed [`Resolve`] and [`PackageId`]. This is a lower level
     /// function for when you have already parsed a binary wit package and have the package ID and
-    /// resolve available. This outputs a component with all exports and an empty imports list.
+    /// resolve available. This outputs a component with all exports, an empty imports list and no
+    /// target, as a package doesn't implement any world itself.
     ///
     /// Returns an error only if the package doesn't exist in the resolve.
-    pub fn from_package(resolve: &Resolve, pkg_id: PackageId) -> anyhow::Result<Self> {
-        let pkg = resolve.packages.get(pkg_id).context("package not found")?;
+    pub fn from_package(resolve: &Resolve, pkg_id: PackageId) -> Result<Self> {
+        let pkg = resolve.packages.get(pkg_id).ok_or(Error::PackageNotFound)?;
         let mut exports = pkg
             .worlds
             .iter()
             .filter_map(|(_name, world_id)| {
                 let world = resolve.worlds.get(*world_id)?;
-                let mut exports = world
-                    .exports
-                    .keys()
-                    .map(|key| resolve.name_world_key(key))
+                let mut exports = WorldItemNames::new(resolve, &world.exports)
+                    .interfaces
+                    .into_iter()
                     .collect::<Vec<_>>();
-                let mut fully_qualified_world =
-                    format!("{}:{}/{}", pkg.name.namespace, pkg.name.name, world.name);
-                if let Some(ver) = pkg.name.version.as_ref() {
-                    fully_qualified_world.push('@');
-                    fully_qualified_world.push_str(&ver.to_string());
-                }
-                exports.push(fully_qualified_world);
+                exports.push(pkg.name.interface_id(&world.name));
                 Some(exports)
             })
             .flatten()
-            .collect::<HashSet<_>>();
+            .collect::<BTreeSet<_>>();
         exports.extend(pkg.interfaces.values().filter_map(|id| resolve.id_of(*id)));
         Ok(Component {
             exports: exports.into_iter().collect(),
-            imports: vec![],
-            target: None,
+            ..Default::default()
         })
     }
 
+    /// Sets `target` to the fully qualified name (e.g. `wasi:http/proxy@0.2.0`) of the world in the
+    /// given package that this component implements. A component implements a world if it provides
+    /// all of the world's exports and only needs imports the world provides. Worlds without any
+    /// exports, such as `wasi:http/imports`, are never picked. If several worlds match, the one
+    /// with the most exports is picked, preferring the one with the fewest imports.
+    ///
+    /// Returns the detected target, or `None` if no world matched, in which case `target` is left
+    /// unchanged. Returns an error only if the package doesn't exist in the resolve.
+    pub fn detect_target(&mut self, resolve: &Resolve, pkg_id: PackageId) -> Result<Option<&str>> {
+        let pkg = resolve.packages.get(pkg_id).ok_or(Error::PackageNotFound)?;
+        if let Some(target) = self.find_target(resolve, pkg.worlds.values().copied()) {
+            self.target = Some(target);
+            return Ok(self.target.as_deref());
+        }
+        Ok(None)
+    }
+
+    /// Checks whether this component can be used as an implementation of the given world: it must
+    /// provide all of the world's exported interfaces and functions and only need interfaces and
+    /// functions the world imports. Types are not checked, as they are always provided along with
+    /// the items using them. Versioned names match if their versions are semver compatible and the
+    /// side providing the interface has the same or a newer version: exports must be at least as
+    /// new as the world's, so a component exporting `wasi:http/handler@0.2.3` conforms to a world
+    /// exporting `wasi:http/handler@0.2.0`, while imports must be at most as new as the world's.
+    /// Returns a report listing any exports that are missing and any imports the world doesn't
+    /// allow.
+    ///
+    /// Returns an error only if the world doesn't exist in the resolve.
+    pub fn satisfies_world(
+        &self,
+        resolve: &Resolve,
+        world_id: WorldId,
+    ) -> Result<WorldConformance> {
+        let world = resolve.worlds.get(world_id).ok_or(Error::WorldNotFound)?;
+        let exports = WorldItemNames::new(resolve, &world.exports);
+        let imports = WorldItemNames::new(resolve, &world.imports);
+        Ok(self.conformance(world_name(resolve, world), &exports, &imports))
+    }
+
+    fn conformance(
+        &self,
+        world: String,
+        exports: &WorldItemNames,
+        imports: &WorldItemNames,
+    ) -> WorldConformance {
+        let missing_interfaces = exports
+            .interfaces
+            .iter()
+            .filter(|name| !self.exports.iter().any(|e| satisfies_interface(e, name)));
+        let missing_functions = exports
+            .functions
+            .iter()
+            .filter(|name| !self.exported_functions.contains(name));
+        let disallowed_interfaces = self.imports.iter().filter(|name| {
+            !imports
+                .interfaces
+                .iter()
+                .any(|i| satisfies_interface(i, name))
+        });
+        let disallowed_functions = self
+            .imported_functions
+            .iter()
+            .filter(|name| !imports.functions.contains(*name));
+        WorldConformance {
+            world,
+            missing_exports: missing_interfaces
+                .chain(missing_functions)
+                .cloned()
+                .collect(),
+            disallowed_imports: disallowed_interfaces
+                .chain(disallowed_functions)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    fn find_target(
+        &self,
+        resolve: &Resolve,
+        candidates: impl IntoIterator<Item = WorldId>,
+    ) -> Option<String> {
+        candidates
+            .into_iter()
+            .filter_map(|id| {
+                let world = resolve.worlds.get(id)?;
+                let exports = WorldItemNames::new(resolve, &world.exports);
+                // Every component trivially implements a world without exports
+                if exports.len() == 0 {
+                    return None;
+                }
+                let imports = WorldItemNames::new(resolve, &world.imports);
+                let report = self.conformance(world_name(resolve, world), &exports, &imports);
+                report
+                    .is_conformant()
+                    .then_some(((exports.len(), Reverse(imports.len())), report.world))
+            })
+            .max_by(|(a, a_name), (b, b_name)| a.cmp(b).then_with(|| b_name.cmp(a_name)))
+            .map(|(_, name)| name)
+    }
+
+    /// Returns the range of WASI versions referenced by the versioned `wasi:*` interfaces this
+    /// component imports or exports, or `None` if it doesn't reference any
+    pub fn wasi_versions(&self) -> Option<WasiVersionRange> {
+        let mut versions = self
+            .imports
+            .iter()
+            .chain(self.exports.iter())
+            .filter_map(|name| wasi_version(name));
+        let first = versions.next()?;
+        let (min, max) = versions.fold((first.clone(), first), |(min, max), version| {
+            (min.min(version.clone()), max.max(version))
+        });
+        Some(WasiVersionRange { min, max })
+    }
+
+    /// Returns the OS this component targets based on the WASI versions it references. Components
+    /// that don't reference any versioned WASI interfaces are assumed to target
+    /// [`WasmOs::Wasip2`]
+    pub fn detect_os(&self) -> WasmOs {
+        self.wasi_versions()
+            .map(|range| range.os())
+            .unwrap_or(WasmOs::Wasip2)
+    }
+
     /// Create a component by loading the given component from the filesystem
-    pub async fn from_component(path: impl AsRef<Path>) -> anyhow::Result<Self> {
-        let data = tokio::fs::read(path).await.context("Unable to read file")?;
+    pub async fn from_component(path: impl AsRef<Path>) -> Result<Self> {
+        let data = tokio::fs::read(path).await?;
         Self::from_raw_component(data)
     }
 
     /// Create a component from the raw bytes of the component
-    pub fn from_raw_component(raw: impl AsRef<[u8]>) -> anyhow::Result<Self> {
-        match wit_component::decode(raw.as_ref()).context("failed to decode WIT component")? {
+    pub fn from_raw_component(raw: impl AsRef<[u8]>) -> Result<Self> {
+        Self::decode(raw.as_ref(), false).map(|(component, _)| component)
+    }
+
+    /// Same as [`Component::from_raw_component`] but also generates the optional
+    /// [`ComponentMetadata`] describing every function and resource the component imports and
+    /// exports
+    pub fn from_raw_component_with_metadata(raw: impl AsRef<[u8]>) -> Result<Self> {
+        Self::decode(raw.as_ref(), true).map(|(component, _)| component)
+    }
+
+    /// Decodes a component or WIT package, also returning which of the two it was
+    pub(crate) fn decode(raw: &[u8], with_metadata: bool) -> Result<(Self, WasmKind)> {
+        match wit_component::decode(raw).map_err(Error::ComponentDecode)? {
             wit_component::DecodedWasm::Component(resolve, world) => {
-                Self::from_world(&resolve, world)
+                let mut component = Self::from_world(&resolve, world)?;
+                if with_metadata {
+                    component.metadata = Some(ComponentMetadata::from_world(&resolve, world)?);
+                }
+                Ok((component, WasmKind::Component))
             }
             wit_component::DecodedWasm::WitPackage(resolve, pkg_id) => {
-                Self::from_package(&resolve, pkg_id)
+                let mut component = Self::from_package(&resolve, pkg_id)?;
+                if with_metadata {
+                    component.metadata = Some(ComponentMetadata::from_package(&resolve, pkg_id)?);
+                }
+                Ok((component, WasmKind::WitPackage))
             }
         }
     }
 }
+
+/// The names of a world's imports or exports, split by the kind of item
+#[derive(Default)]
+struct WorldItemNames {
+    interfaces: BTreeSet<String>,
+    functions: BTreeSet<String>,
+    types: BTreeSet<String>,
+}
+
+impl WorldItemNames {
+    fn new<'a>(
+        resolve: &Resolve,
+        items: impl IntoIterator<Item = (&'a WorldKey, &'a WorldItem)>,
+    ) -> Self {
+        let mut names = Self::default();
+        for (key, item) in items {
+            let set = match item {
+                WorldItem::Interface { .. } => &mut names.interfaces,
+                WorldItem::Function(_) => &mut names.functions,
+                WorldItem::Type(_) => &mut names.types,
+            };
+            set.insert(resolve.name_world_key(key));
+        }
+        names
+    }
+
+    /// The number of items that take part in conformance checks, which excludes types
+    fn len(&self) -> usize {
+        self.interfaces.len() + self.functions.len()
+    }
+}
+
+/// Returns the fully qualified name of the given world, or just its name if it isn't part of a
+/// package
+fn world_name(resolve: &Resolve, world: &World) -> String {
+    match world.package.and_then(|id| resolve.packages.get(id)) {
+        Some(pkg) => pkg.name.interface_id(&world.name),
+        None => world.name.clone(),
+    }
+}
+
+/// Inspects the header of a wasm binary to tell core modules apart from components. Components and
+/// WIT packages share the same header, so this returns [`WasmKind::Component`] for both.
+///
+/// Returns [`Error::NotWasm`] if the header isn't a wasm header
+pub(crate) fn detect_kind(raw: &[u8]) -> Result<WasmKind> {
+    let Some(header) = raw.get(..8).filter(|header| header.starts_with(WASM_MAGIC)) else {
+        return Err(Error::NotWasm);
+    };
+    if &header[4..] == MODULE_VERSION {
+        Ok(WasmKind::Module)
+    } else if &header[6..] == COMPONENT_LAYER {
+        Ok(WasmKind::Component)
+    } else {
+        Err(Error::NotWasm)
+    }
+}
+
+/// Parses the version out of a fully qualified WASI interface name such as
+/// `wasi:cli/environment@0.2.0`
+fn wasi_version(name: &str) -> Option<Version> {
+    let (namespace, _) = name.split_once(':')?;
+    if namespace != WASI_NAMESPACE {
+        return None;
+    }
+    split_version(name).1
+}
@@ -1,20 +1,25 @@
 use std::collections::BTreeMap;
 
-use anyhow::Context;
 use chrono::{DateTime, Utc};
 use oci_client::client::{Config, ImageLayer};
 use serde::{Deserialize, Serialize};
 use sha2::Digest;
+use tokio::io::AsyncReadExt;
+use wit_parser::{PackageId, Resolve};
 
 use crate::{
-    Component, COMPONENT_OS, MODULE_OS, WASM_ARCHITECTURE, WASM_LAYER_MEDIA_TYPE,
-    WASM_MANIFEST_CONFIG_MEDIA_TYPE,
+    component::detect_kind, embedded_signature::embed_signature, Component, ConfigViolation, Error,
+    HostCapabilities, HostCompatibility, Module, Result, SigningKey, WasmKind, WasmOs,
+    WASM_ARCHITECTURE, WASM_LAYER_MEDIA_TYPE, WASM_MANIFEST_CONFIG_MEDIA_TYPE,
 };
 
+/// The size of the chunks used when streaming layers from disk
+pub(crate) const STREAM_CHUNK_SIZE: usize = 1024 * 1024;
+
 // A convenience trait that indicates a type can be converted into an OCI manifest config
 pub trait ToConfig {
     /// Convert the type into an OCI manifest config
-    fn to_config(&self) -> anyhow::Result<Config>;
+    fn to_config(&self) -> Result<Config>;
 }
 
 /// The config type struct for `application/wasm`
@@ -27,11 +32,12 @@ pub struct WasmConfig {
     pub author: Option<String>,
     /// The architecture of the artifact. This is always `wasm`.
     pub architecture: String,
-    /// The OS name of the artifact. Possible options: wasip1, wasip2. For plain wasm, this should
-    /// be wasip1 as this must match a GOOS value and it doesn’t have one for plain Wasm
+    /// The OS name of the artifact. See [`WasmOs`] for the possible options. For plain wasm, this
+    /// should be [`WasmOs::Wasip1`] as this must match a GOOS value and it doesn’t have one for
+    /// plain Wasm
     ///
     /// Eventually this will go away when we hit a 1.0 but we need it for now
-    pub os: String,
+    pub os: WasmOs,
     /// This field contains a list of digests of each of the layers from the manifest in the same
     /// order as they are listed in the manfiest. This exists because we need to have a unique list
     /// here so that the hash of the config (used as the ID) is unique every time
@@ -40,6 +46,115 @@ pub struct WasmConfig {
     /// Information about the component in the manifest. This is required when the `os` field is
     /// `wasip2`
     pub component: Option<Component>,
+    /// Information about the imports and exports of a plain wasm module. This isn't part of the
+    /// OCI Wasm specification and is only set for modules
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub module: Option<Module>,
+    /// The id of the key that signed the layer with a signature embedded in its `signature` custom
+    /// section, as returned by [`VerifyingKey::key_id`](crate::VerifyingKey::key_id). This isn't
+    /// part of the OCI Wasm specification and is only set by
+    /// [`WasmConfig::from_raw_component_signed`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer_key_id: Option<String>,
+}
+
+/// A builder for a [`WasmConfig`], created with [`WasmConfig::builder`]. Unlike the `from_*`
+/// helpers, every field can be set explicitly and the result is validated when calling
+/// [`WasmConfigBuilder::build`]
+#[derive(Debug, Default)]
+pub struct WasmConfigBuilder {
+    created: Option<DateTime<Utc>>,
+    author: Option<String>,
+    architecture: Option<String>,
+    os: Option<WasmOs>,
+    layer_digests: Vec<String>,
+    component: Option<Component>,
+    module: Option<Module>,
+    signer_key_id: Option<String>,
+}
+
+impl WasmConfigBuilder {
+    /// Sets the created time. Defaults to [`WasmConfig::default_created`]
+    pub fn created(mut self, created: DateTime<Utc>) -> Self {
+        self.created = Some(created);
+        self
+    }
+
+    /// Sets the author of the config
+    pub fn author(mut self, author: impl Into<String>) -> Self {
+        self.author = Some(author.into());
+        self
+    }
+
+    /// Sets the architecture. Defaults to `wasm`, which is the only valid value
+    pub fn architecture(mut self, architecture: impl Into<String>) -> Self {
+        self.architecture = Some(architecture.into());
+        self
+    }
+
+    /// Sets the OS. Defaults to the OS detected from the component information with
+    /// [`Component::detect_os`] if it is set and `wasip1` otherwise
+    pub fn os(mut self, os: impl Into<WasmOs>) -> Self {
+        self.os = Some(os.into());
+        self
+    }
+
+    /// Sets the component information
+    pub fn component(mut self, component: Component) -> Self {
+        self.component = Some(component);
+        self
+    }
+
+    /// Sets the module information
+    pub fn module(mut self, module: Module) -> Self {
+        self.module = Some(module);
+        self
+    }
+
+    /// Sets the id of the key whose signature is embedded in the layer
+    pub fn signer_key_id(mut self, key_id: impl Into<String>) -> Self {
+        self.signer_key_id = Some(key_id.into());
+        self
+    }
+
+    /// Adds a layer to the config by computing its digest
+    pub fn layer(mut self, layer: &ImageLayer) -> Self {
+        self.layer_digests.push(sha256_digest(&layer.data));
+        self
+    }
+
+    /// Adds the digest of a layer to the config. The digest must be in the form
+    /// `sha256:<hex encoded digest>`
+    pub fn layer_digest(mut self, digest: impl Into<String>) -> Self {
+        self.layer_digests.push(digest.into());
+        self
+    }
+
+    /// Builds the config, returning [`Error::InvalidConfig`] with all violations found if the
+    /// config isn't valid according to [`WasmConfig::validate`]
+    pub fn build(self) -> Result<WasmConfig> {
+        let os = self.os.unwrap_or_else(|| match &self.component {
+            Some(component) => component.detect_os(),
+            None => WasmOs::Wasip1,
+        });
+        let config = WasmConfig {
+            created: match self.created {
+                Some(created) => created,
+                None => WasmConfig::default_created()?,
+            },
+            author: self.author,
+            architecture: self
+                .architecture
+                .unwrap_or_else(|| WASM_ARCHITECTURE.to_string()),
+            os,
+            layer_digests: self.layer_digests,
+            component: self.component,
+            module: self.module,
+            signer_key_id: self.signer_key_id,
+        };
+        config.validate()?;
+        Ok(config)
+    }
 }
 
 pub struct AnnotatedWasmConfig<'a> {
@@ -48,30 +163,148 @@ pub struct AnnotatedWasmConfig<'a> {
 }
 
 impl WasmConfig {
+    /// Returns a [`WasmConfigBuilder`] for building a config with explicitly set fields
+    pub fn builder() -> WasmConfigBuilder {
+        WasmConfigBuilder::default()
+    }
+
+    /// Returns the created time used by the `from_*` helpers and [`WasmConfigBuilder`] when none is
+    /// set explicitly. This honors the
+    /// [`SOURCE_DATE_EPOCH`](https://reproducible-builds.org/specs/source-date-epoch/) environment
+    /// variable so that builds can be reproduced, and falls back to the current time if it isn't
+    /// set.
+    ///
+    /// Returns [`Error::InvalidSourceDateEpoch`] if the variable is set to something other than a
+    /// Unix timestamp
+    pub fn default_created() -> Result<DateTime<Utc>> {
+        match std::env::var("SOURCE_DATE_EPOCH") {
+            Ok(epoch) => epoch
+                .trim()
+                .parse::<i64>()
+                .ok()
+                .and_then(|secs| DateTime::from_timestamp(secs, 0))
+                .ok_or(Error::InvalidSourceDateEpoch(epoch)),
+            Err(_) => Ok(Utc::now()),
+        }
+    }
+
     /// A helper for loading a component from a file and returning the proper config and
-    /// [`ImageLayer`]. The returned config will have the created time set to now and all other
-    /// fields set for a component.
+    /// [`ImageLayer`]. The returned config will have the created time set to
+    /// [`WasmConfig::default_created`], the OS detected from the WASI versions the component
+    /// references (see [`Component::detect_os`]) and all other fields set for a component.
     pub async fn from_component(
         path: impl AsRef<std::path::Path>,
         author: Option<String>,
-    ) -> anyhow::Result<(Self, ImageLayer)> {
-        let raw = tokio::fs::read(path).await.context("Unable to read file")?;
+    ) -> Result<(Self, ImageLayer)> {
+        let raw = tokio::fs::read(path).await?;
         Self::from_raw_component(raw, author)
     }
 
     /// Same as [`WasmConfig::from_component`] but for raw component bytes
-    pub fn from_raw_component(
+    pub fn from_raw_component(raw: Vec<u8>, author: Option<String>) -> Result<(Self, ImageLayer)> {
+        let component = Component::from_raw_component(&raw)?;
+        Self::component_config(raw, author, component)
+    }
+
+    /// Same as [`WasmConfig::from_component`], but also fills in the optional
+    /// [`ComponentMetadata`](crate::ComponentMetadata) describing every function, resource and type
+    /// the component imports and exports. See [`WasmConfig::from_raw_component_with_metadata`]
+    pub async fn from_component_with_metadata(
+        path: impl AsRef<std::path::Path>,
+        author: Option<String>,
+    ) -> Result<(Self, ImageLayer)> {
+        let raw = tokio::fs::read(path).await?;
+        Self::from_raw_component_with_metadata(raw, author)
+    }
+
+    /// Same as [`WasmConfig::from_raw_component`], but the component information also contains the
+    /// optional metadata generated by [`Component::from_raw_component_with_metadata`]. This makes
+    /// the config larger, so only use it when consumers need the metadata
+    pub fn from_raw_component_with_metadata(
         raw: Vec<u8>,
         author: Option<String>,
-    ) -> anyhow::Result<(Self, ImageLayer)> {
+    ) -> Result<(Self, ImageLayer)> {
+        let component = Component::from_raw_component_with_metadata(&raw)?;
+        Self::component_config(raw, author, component)
+    }
+
+    /// Same as [`WasmConfig::from_component`], but also sets the component's `target` to the world
+    /// in the given WIT package that it implements. See
+    /// [`WasmConfig::from_raw_component_with_target`]
+    pub async fn from_component_with_target(
+        path: impl AsRef<std::path::Path>,
+        author: Option<String>,
+        resolve: &Resolve,
+        pkg_id: PackageId,
+    ) -> Result<(Self, ImageLayer)> {
+        let raw = tokio::fs::read(path).await?;
+        Self::from_raw_component_with_target(raw, author, resolve, pkg_id)
+    }
+
+    /// Same as [`WasmConfig::from_raw_component`], but also detects the world in the given WIT
+    /// package, such as `wasi:http`, that the component implements with
+    /// [`Component::detect_target`] and records it as the component's `target`. A compiled
+    /// component only carries the interfaces it uses, so this is how `target` is set for one. If
+    /// no world matches, `target` is left unset.
+    ///
+    /// Returns [`Error::PackageNotFound`] if the package doesn't exist in the resolve
+    pub fn from_raw_component_with_target(
+        raw: Vec<u8>,
+        author: Option<String>,
+        resolve: &Resolve,
+        pkg_id: PackageId,
+    ) -> Result<(Self, ImageLayer)> {
+        let mut component = Component::from_raw_component(&raw)?;
+        component.detect_target(resolve, pkg_id)?;
+        Self::component_config(raw, author, component)
+    }
+
+    /// Same as [`WasmConfig::from_component`], but signs the component with the given ed25519
+    /// key first. See [`WasmConfig::from_raw_component_signed`]
+    pub async fn from_component_signed(
+        path: impl AsRef<std::path::Path>,
+        author: Option<String>,
+        key: &SigningKey,
+    ) -> Result<(Self, ImageLayer)> {
+        let raw = tokio::fs::read(path).await?;
+        Self::from_raw_component_signed(raw, author, key)
+    }
+
+    /// Same as [`WasmConfig::from_raw_component`], but first signs the component with the given
+    /// key and embeds the signature in a `signature` custom section, using the
+    /// [wasm-signatures](https://github.com/wasm-signatures/design) format. The returned layer
+    /// contains the signed component and the config records the id of the key in
+    /// `signer_key_id`. The signature can be checked when pulling with
+    /// [`WasmClient::with_trusted_keys`](crate::WasmClient::with_trusted_keys).
+    ///
+    /// Returns [`Error::InvalidKey`] if the key isn't an ed25519 key, as that is the only
+    /// algorithm defined by the format
+    pub fn from_raw_component_signed(
+        raw: Vec<u8>,
+        author: Option<String>,
+        key: &SigningKey,
+    ) -> Result<(Self, ImageLayer)> {
         let component = Component::from_raw_component(&raw)?;
+        let (signed, key_id) = embed_signature(&raw, key)?;
+        let (mut config, layer) = Self::component_config(signed, author, component)?;
+        config.signer_key_id = Some(key_id);
+        Ok((config, layer))
+    }
+
+    fn component_config(
+        raw: Vec<u8>,
+        author: Option<String>,
+        component: Component,
+    ) -> Result<(Self, ImageLayer)> {
         let config = Self {
-            created: Utc::now(),
+            created: Self::default_created()?,
             author,
             architecture: WASM_ARCHITECTURE.to_string(),
-            os: COMPONENT_OS.to_string(),
+            os: component.detect_os(),
             layer_digests: vec![sha256_digest(&raw)],
             component: Some(component),
+            module: None,
+            signer_key_id: None,
         };
         Ok((
             config,
@@ -84,28 +317,31 @@ impl WasmConfig {
     }
 
     /// A helper for loading a plain wasm module and returning the proper config and [`ImageLayer`].
-    /// The returned config will have the created time set to now and all other fields set for a
-    /// plain wasm module.
+    /// The returned config will have the created time set to [`WasmConfig::default_created`], the
+    /// `module` field set to the module's imports and exports and all other fields set for a plain
+    /// wasm module.
+    ///
+    /// Returns [`Error::ModuleDecode`] if the file isn't a valid core module
     pub async fn from_module(
         path: impl AsRef<std::path::Path>,
         author: Option<String>,
-    ) -> anyhow::Result<(Self, ImageLayer)> {
-        let raw = tokio::fs::read(path).await.context("Unable to read file")?;
+    ) -> Result<(Self, ImageLayer)> {
+        let raw = tokio::fs::read(path).await?;
         Self::from_raw_module(raw, author)
     }
 
     /// Same as [`WasmConfig::from_module`] but for raw module bytes
-    pub fn from_raw_module(
-        raw: Vec<u8>,
-        author: Option<String>,
-    ) -> anyhow::Result<(Self, ImageLayer)> {
+    pub fn from_raw_module(raw: Vec<u8>, author: Option<String>) -> Result<(Self, ImageLayer)> {
+        let module = Module::from_raw_module(&raw)?;
         let config = Self {
-            created: Utc::now(),
+            created: Self::default_created()?,
             author,
             architecture: WASM_ARCHITECTURE.to_string(),
-            os: MODULE_OS.to_string(),
+            os: WasmOs::Wasip1,
             layer_digests: vec![sha256_digest(&raw)],
             component: None,
+            module: Some(module),
+            signer_key_id: None,
         };
         Ok((
             config,
@@ -117,6 +353,117 @@ impl WasmConfig {
         ))
     }
 
+    /// A helper for loading any wasm binary from a file and returning the proper config and
+    /// [`ImageLayer`], along with the kind of binary found. The binary's header is inspected so
+    /// core modules are handled like [`WasmConfig::from_module`], while components and WIT packages
+    /// are handled like [`WasmConfig::from_component`].
+    ///
+    /// Returns [`Error::NotWasm`] if the file isn't a wasm binary
+    pub async fn from_wasm(
+        path: impl AsRef<std::path::Path>,
+        author: Option<String>,
+    ) -> Result<(Self, ImageLayer, WasmKind)> {
+        let raw = tokio::fs::read(path).await?;
+        Self::from_raw_wasm(raw, author)
+    }
+
+    /// Same as [`WasmConfig::from_wasm`] but for raw wasm bytes
+    pub fn from_raw_wasm(
+        raw: Vec<u8>,
+        author: Option<String>,
+    ) -> Result<(Self, ImageLayer, WasmKind)> {
+        if detect_kind(&raw)? == WasmKind::Module {
+            let (config, layer) = Self::from_raw_module(raw, author)?;
+            return Ok((config, layer, WasmKind::Module));
+        }
+        let (component, kind) = Component::decode(&raw, false)?;
+        let (config, layer) = Self::component_config(raw, author, component)?;
+        Ok((config, layer, kind))
+    }
+
+    /// Checks whether the given host provides every import of the component described by this
+    /// config, using [`Component::check_host`]. Configs without component information, such as
+    /// those for plain modules, don't list any imports and are always reported as compatible
+    pub fn check_host(&self, host: &HostCapabilities) -> HostCompatibility {
+        self.component
+            .as_ref()
+            .map(|component| component.check_host(host))
+            .unwrap_or_default()
+    }
+
+    /// Verifies that the given layers match the digests listed in `layer_digests`, in order.
+    ///
+    /// Returns [`Error::WrongLayerCount`] if the number of layers doesn't match the number of
+    /// digests and [`Error::LayerDigestMismatch`] if any of the digests don't match
+    pub fn verify_layers(&self, layers: &[ImageLayer]) -> Result<()> {
+        if layers.len() != self.layer_digests.len() {
+            return Err(Error::WrongLayerCount(layers.len()));
+        }
+        for (layer, expected) in layers.iter().zip(self.layer_digests.iter()) {
+            let actual = sha256_digest(&layer.data);
+            if &actual != expected {
+                return Err(Error::LayerDigestMismatch {
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses a config and validates it with [`WasmConfig::validate`]. Unlike the `TryFrom`
+    /// implementations, which accept any well-formed JSON, this rejects configs that violate the
+    /// OCI Wasm specification
+    pub fn parse_strict(data: impl AsRef<[u8]>) -> Result<Self> {
+        let config = Self::try_from(data.as_ref())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Validates this config against the rules of the OCI Wasm specification, returning
+    /// [`Error::InvalidConfig`] with every violation found
+    pub fn validate(&self) -> Result<()> {
+        let mut violations = Vec::new();
+        if self.architecture != WASM_ARCHITECTURE {
+            violations.push(ConfigViolation::WrongArchitecture(
+                self.architecture.clone(),
+            ));
+        }
+        if let WasmOs::Unknown(os) = &self.os {
+            violations.push(ConfigViolation::UnknownOs(os.clone()));
+        }
+        if self.os.is_component() && self.component.is_none() {
+            violations.push(ConfigViolation::MissingComponent(self.os.clone()));
+        }
+        if self.layer_digests.is_empty() {
+            violations.push(ConfigViolation::MissingLayerDigests);
+        }
+        violations.extend(
+            self.layer_digests
+                .iter()
+                .filter(|digest| !is_sha256_digest(digest))
+                .map(|digest| ConfigViolation::MalformedLayerDigest(digest.clone())),
+        );
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidConfig(violations))
+        }
+    }
+
+    /// Verifies that this config lists exactly one layer with the given digest
+    pub(crate) fn verify_layer_digest(&self, digest: &str) -> Result<()> {
+        match self.layer_digests.as_slice() {
+            [expected] if expected == digest => Ok(()),
+            [expected] => Err(Error::LayerDigestMismatch {
+                expected: expected.clone(),
+                actual: digest.to_string(),
+            }),
+            digests => Err(Error::WrongLayerCount(digests.len())),
+        }
+    }
+
     /// Adds annotations to this [`WasmConfig`].
     #[must_use]
     pub fn with_annotations(
@@ -130,9 +477,15 @@ impl WasmConfig {
     }
 }
 
+impl<T: ToConfig + ?Sized> ToConfig for &T {
+    fn to_config(&self) -> Result<Config> {
+        (**self).to_config()
+    }
+}
+
 impl ToConfig for AnnotatedWasmConfig<'_> {
     /// Generate a [`Config`] for this [`WasmConfig`]
-    fn to_config(&self) -> anyhow::Result<Config> {
+    fn to_config(&self) -> Result<Config> {
         let mut config = self.config.to_config()?;
         config.annotations = Some(self.annotations.clone());
         Ok(config)
@@ -141,14 +494,14 @@ impl ToConfig for AnnotatedWasmConfig<'_> {
 
 impl ToConfig for WasmConfig {
     /// Generate a [`Config`] for this [`WasmConfig`]
-    fn to_config(&self) -> anyhow::Result<Config> {
+    fn to_config(&self) -> Result<Config> {
         serde_json::to_vec(self)
             .map(|data| Config {
                 data: data.into(),
                 media_type: WASM_MANIFEST_CONFIG_MEDIA_TYPE.to_string(),
                 annotations: None,
             })
-            .map_err(Into::into)
+            .map_err(Error::ConfigSerialize)
     }
 }
 
@@ -156,37 +509,65 @@ impl ToConfig for WasmConfig {
 // across T for AsRef<[u8]>
 
 impl TryFrom<String> for WasmConfig {
-    type Error = anyhow::Error;
+    type Error = Error;
 
-    fn try_from(value: String) -> Result<Self, Self::Error> {
-        serde_json::from_str(&value).map_err(Into::into)
+    fn try_from(value: String) -> Result<Self> {
+        serde_json::from_str(&value).map_err(Error::ConfigParse)
     }
 }
 
 impl TryFrom<Vec<u8>> for WasmConfig {
-    type Error = anyhow::Error;
+    type Error = Error;
 
-    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
-        serde_json::from_slice(&value).map_err(Into::into)
+    fn try_from(value: Vec<u8>) -> Result<Self> {
+        serde_json::from_slice(&value).map_err(Error::ConfigParse)
     }
 }
 
 impl TryFrom<&str> for WasmConfig {
-    type Error = anyhow::Error;
+    type Error = Error;
 
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        serde_json::from_str(value).map_err(Into::into)
+    fn try_from(value: &str) -> Result<Self> {
+        serde_json::from_str(value).map_err(Error::ConfigParse)
     }
 }
 
 impl TryFrom<&[u8]> for WasmConfig {
-    type Error = anyhow::Error;
+    type Error = Error;
 
-    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        serde_json::from_slice(value).map_err(Into::into)
+    fn try_from(value: &[u8]) -> Result<Self> {
+        serde_json::from_slice(value).map_err(Error::ConfigParse)
     }
 }
 
-fn sha256_digest(bytes: &[u8]) -> String {
+pub(crate) fn sha256_digest(bytes: &[u8]) -> String {
     format!("sha256:{:x}", sha2::Sha256::digest(bytes))
 }
+
+/// Checks that the digest is a well-formed sha256 digest
+pub(crate) fn is_sha256_digest(digest: &str) -> bool {
+    digest.strip_prefix("sha256:").is_some_and(|encoded| {
+        encoded.len() == 64
+            && encoded
+                .bytes()
+                .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+    })
+}
+
+/// Computes the sha256 digest and size of the file at the given path without reading the whole
+/// file into memory
+pub(crate) async fn sha256_digest_file(path: impl AsRef<std::path::Path>) -> Result<(String, u64)> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = sha2::Sha256::new();
+    let mut buf = vec![0; STREAM_CHUNK_SIZE];
+    let mut size = 0;
+    loop {
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        size += n as u64;
+    }
+    Ok((format!("sha256:{:x}", hasher.finalize()), size))
+}
@@ -0,0 +1,296 @@
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, AsyncReadExt, BufReader};
+
+use crate::{config::STREAM_CHUNK_SIZE, signature::SigningKey, Error, Result, VerifyingKey};
+
+/// The name of the custom section holding the signature
+const SIGNATURE_SECTION: &str = "signature";
+/// The version of the signature format
+const SPEC_VERSION: u8 = 0x01;
+/// The content type for wasm binaries
+const CONTENT_TYPE_WASM: u8 = 0x01;
+/// The identifier of SHA-256, the only hash function defined by the format
+const HASH_SHA256: u8 = 0x01;
+/// The identifier of ed25519, the only signature algorithm defined by the format
+const ALG_ED25519: u8 = 0x01;
+/// The domain separator prepended to the hash before signing
+const DOMAIN: &[u8] = b"wasmsig";
+/// The length of the magic number and version at the start of every wasm binary
+const HEADER_LEN: usize = 8;
+/// The largest signature section that is read into memory when verifying a file. Real signature
+/// sections are a few hundred bytes, so files with bigger ones are treated as unsigned
+const MAX_SIGNATURE_SECTION_SIZE: u32 = 64 * 1024;
+
+/// A top level section of a wasm binary
+struct Section<'a> {
+    /// The whole section, including its id and size
+    bytes: &'a [u8],
+    /// The contents of the section if it is the signature section
+    signature: Option<&'a [u8]>,
+}
+
+/// Signs the given wasm binary with the key, returning the binary with the signature embedded and
+/// the id of the key. This follows the [wasm-signatures](https://github.com/wasm-signatures/design)
+/// format used by `wasmsign2`: a `signature` custom section is placed right after the header and
+/// signs the SHA-256 hash of everything else in the binary. Any signature section already present
+/// is replaced. Only ed25519 keys are supported, as that is the only algorithm defined by the
+/// format
+pub(crate) fn embed_signature(raw: &[u8], key: &SigningKey) -> Result<(Vec<u8>, String)> {
+    if !matches!(key, SigningKey::Ed25519(_)) {
+        return Err(Error::InvalidKey(
+            "embedded signatures require an ed25519 key".into(),
+        ));
+    }
+    let (header, sections) = split_sections(raw).ok_or(Error::NotWasm)?;
+    let hash = hash_without_signature(header, &sections);
+    let signature = key.sign(&signed_message(&hash));
+    let key_id = key.verifying_key().key_id();
+
+    let mut contents = vec![SPEC_VERSION, CONTENT_TYPE_WASM, HASH_SHA256];
+    // A single set containing a single hash signed by a single key
+    write_u32(&mut contents, 1);
+    write_u32(&mut contents, 1);
+    contents.extend_from_slice(&hash);
+    write_u32(&mut contents, 1);
+    write_bytes(&mut contents, key_id.as_bytes());
+    contents.push(ALG_ED25519);
+    write_bytes(&mut contents, &signature);
+
+    let mut section = Vec::new();
+    write_bytes(&mut section, SIGNATURE_SECTION.as_bytes());
+    section.extend_from_slice(&contents);
+
+    let mut signed = Vec::with_capacity(raw.len() + section.len() + 6);
+    signed.extend_from_slice(header);
+    signed.push(0);
+    write_bytes(&mut signed, &section);
+    for section in sections.iter().filter(|s| s.signature.is_none()) {
+        signed.extend_from_slice(section.bytes);
+    }
+    Ok((signed, key_id))
+}
+
+/// Verifies the signature embedded in the given wasm binary, returning the id of the first of the
+/// keys that made a valid signature. Returns `None` if the binary isn't signed by any of the keys
+pub(crate) fn verify_embedded_signature(raw: &[u8], keys: &[VerifyingKey]) -> Option<String> {
+    let (header, sections) = split_sections(raw)?;
+    let hash = hash_without_signature(header, &sections);
+    let contents = sections.iter().find_map(|s| s.signature)?;
+    verify_signature_section(&hash, contents, keys)
+}
+
+/// Same as [`verify_embedded_signature`], but reads the wasm binary from the given reader section
+/// by section, so only the signature section is ever held in memory
+pub(crate) async fn verify_embedded_signature_stream(
+    reader: impl AsyncRead + Unpin,
+    keys: &[VerifyingKey],
+) -> Result<Option<String>> {
+    match hash_stream_without_signature(BufReader::new(reader)).await {
+        Ok(Some((hash, Some(contents)))) => Ok(verify_signature_section(&hash, &contents, keys)),
+        Ok(_) => Ok(None),
+        // A truncated binary is malformed, so it is treated like any other unsigned binary
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Verifies the contents of a signature section against the hash of the rest of the binary
+fn verify_signature_section(
+    hash: &[u8],
+    mut contents: &[u8],
+    keys: &[VerifyingKey],
+) -> Option<String> {
+    let message = signed_message(hash);
+    if take(&mut contents, 3)? != [SPEC_VERSION, CONTENT_TYPE_WASM, HASH_SHA256] {
+        return None;
+    }
+    for _ in 0..read_u32(&mut contents)? {
+        let mut signs_hash = false;
+        for _ in 0..read_u32(&mut contents)? {
+            signs_hash |= take(&mut contents, hash.len())? == hash;
+        }
+        for _ in 0..read_u32(&mut contents)? {
+            read_bytes(&mut contents)?;
+            let alg = take(&mut contents, 1)?[0];
+            let signature = read_bytes(&mut contents)?;
+            if !signs_hash || alg != ALG_ED25519 {
+                continue;
+            }
+            if let Some(key) = keys.iter().find(|key| {
+                matches!(key, VerifyingKey::Ed25519(_)) && key.verify(&message, signature)
+            }) {
+                return Some(key.key_id());
+            }
+        }
+    }
+    None
+}
+
+/// Splits a wasm binary into its header and top level sections, returning `None` if it is
+/// malformed
+fn split_sections(raw: &[u8]) -> Option<(&[u8], Vec<Section<'_>>)> {
+    if raw.len() < HEADER_LEN || !raw.starts_with(b"\0asm") {
+        return None;
+    }
+    let (header, mut rest) = raw.split_at(HEADER_LEN);
+    let mut sections = Vec::new();
+    while !rest.is_empty() {
+        let start = rest;
+        let id = take(&mut rest, 1)?[0];
+        let mut contents = read_bytes(&mut rest)?;
+        let bytes = &start[..start.len() - rest.len()];
+        let signature = (id == 0 && read_bytes(&mut contents)? == SIGNATURE_SECTION.as_bytes())
+            .then_some(contents);
+        sections.push(Section { bytes, signature });
+    }
+    Some((header, sections))
+}
+
+fn hash_without_signature(header: &[u8], sections: &[Section<'_>]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(header);
+    for section in sections.iter().filter(|s| s.signature.is_none()) {
+        hasher.update(section.bytes);
+    }
+    hasher.finalize().to_vec()
+}
+
+/// Streaming counterpart of [`split_sections`] and [`hash_without_signature`]. Returns the hash
+/// and the contents of the first signature section, or `None` if the binary is malformed
+async fn hash_stream_without_signature(
+    mut reader: impl AsyncRead + Unpin,
+) -> std::io::Result<Option<(Vec<u8>, Option<Vec<u8>>)>> {
+    let mut header = [0; HEADER_LEN];
+    reader.read_exact(&mut header).await?;
+    if !header.starts_with(b"\0asm") {
+        return Ok(None);
+    }
+    let mut hasher = Sha256::new();
+    hasher.update(header);
+    let mut signature = None;
+    let mut buf = vec![0; STREAM_CHUNK_SIZE];
+    loop {
+        // The id, size and, for custom sections, the name of the section as they appear in the
+        // binary, so they can be hashed unless this turns out to be the signature section
+        let mut prefix = Vec::new();
+        let mut id = [0];
+        if reader.read(&mut id).await? == 0 {
+            break;
+        }
+        prefix.push(id[0]);
+        let Some(size) = read_u32_from(&mut reader, &mut prefix).await? else {
+            return Ok(None);
+        };
+        let contents_start = prefix.len();
+        let mut is_signature = false;
+        if id[0] == 0 {
+            let Some(name_len) = read_u32_from(&mut reader, &mut prefix).await? else {
+                return Ok(None);
+            };
+            if prefix.len() - contents_start + name_len as usize > size as usize {
+                return Ok(None);
+            }
+            if name_len as usize == SIGNATURE_SECTION.len() {
+                let mut name = [0; SIGNATURE_SECTION.len()];
+                reader.read_exact(&mut name).await?;
+                prefix.extend_from_slice(&name);
+                is_signature = name == SIGNATURE_SECTION.as_bytes();
+            }
+        }
+        let Some(mut remaining) = (size as usize).checked_sub(prefix.len() - contents_start) else {
+            return Ok(None);
+        };
+
+        if is_signature {
+            if size > MAX_SIGNATURE_SECTION_SIZE {
+                return Ok(None);
+            }
+            let mut contents = vec![0; remaining];
+            reader.read_exact(&mut contents).await?;
+            signature.get_or_insert(contents);
+            continue;
+        }
+        hasher.update(&prefix);
+        while remaining > 0 {
+            let n = remaining.min(buf.len());
+            reader.read_exact(&mut buf[..n]).await?;
+            hasher.update(&buf[..n]);
+            remaining -= n;
+        }
+    }
+    Ok(Some((hasher.finalize().to_vec(), signature)))
+}
+
+fn signed_message(hash: &[u8]) -> Vec<u8> {
+    let mut message = DOMAIN.to_vec();
+    message.extend_from_slice(&[SPEC_VERSION, CONTENT_TYPE_WASM, HASH_SHA256]);
+    message.extend_from_slice(hash);
+    message
+}
+
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if data.len() < len {
+        return None;
+    }
+    let (taken, rest) = data.split_at(len);
+    *data = rest;
+    Some(taken)
+}
+
+/// Reads an unsigned LEB128 encoded 32 bit integer
+fn read_u32(data: &mut &[u8]) -> Option<u32> {
+    let mut value = 0u32;
+    for shift in (0..35).step_by(7) {
+        let byte = take(data, 1)?[0];
+        value |= u32::from(byte & 0x7f).checked_shl(shift)?;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Same as [`read_u32`], but reads from the given reader and appends the raw bytes to `raw`
+async fn read_u32_from(
+    reader: &mut (impl AsyncRead + Unpin),
+    raw: &mut Vec<u8>,
+) -> std::io::Result<Option<u32>> {
+    let mut value = 0u32;
+    for shift in (0..35).step_by(7) {
+        let byte = reader.read_u8().await?;
+        raw.push(byte);
+        let Some(bits) = u32::from(byte & 0x7f).checked_shl(shift) else {
+            return Ok(None);
+        };
+        value |= bits;
+        if byte & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+    }
+    Ok(None)
+}
+
+/// Reads a byte vector prefixed with its length
+fn read_bytes<'a>(data: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let len = read_u32(data)? as usize;
+    take(data, len)
+}
+
+/// Writes an unsigned LEB128 encoded 32 bit integer
+fn write_u32(out: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Writes a byte vector prefixed with its length
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_u32(out, bytes.len() as u32);
+    out.extend_from_slice(bytes);
+}
@@ -0,0 +1,151 @@
+use oci_client::errors::OciDistributionError;
+
+use crate::{
+    WasmOs, WorldConformance, ASYNC_COMPONENT_OS, COMPONENT_OS, MODULE_OS, WASM_ARCHITECTURE,
+    WASM_MANIFEST_CONFIG_MEDIA_TYPE, WASM_MANIFEST_MEDIA_TYPE,
+};
+
+/// A convenience alias for results returned by this crate
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// All errors that can be returned when working with Wasm artifacts
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The artifact did not contain exactly one layer. Contains the number of layers found
+    #[error("Wasm components must have exactly one layer, found {0}")]
+    WrongLayerCount(usize),
+    /// The manifest had a media type other than [`WASM_MANIFEST_MEDIA_TYPE`]. Contains the media
+    /// type that was found
+    #[error("Wasm components must have a manifest of type {WASM_MANIFEST_MEDIA_TYPE}, found {0}")]
+    WrongManifestMediaType(String),
+    /// The config had a media type other than [`WASM_MANIFEST_CONFIG_MEDIA_TYPE`]. Contains the
+    /// media type that was found
+    #[error(
+        "Wasm components must have a config of type {WASM_MANIFEST_CONFIG_MEDIA_TYPE}, found {0}"
+    )]
+    WrongConfigMediaType(String),
+    /// The digest of a pulled layer did not match the digest recorded in the manifest or the
+    /// config
+    #[error("layer digest mismatch: expected {expected}, got {actual}")]
+    LayerDigestMismatch {
+        /// The digest that was recorded for the layer
+        expected: String,
+        /// The digest computed from the layer bytes
+        actual: String,
+    },
+    /// A pull didn't return the manifest, so the pulled layers couldn't be verified against it
+    #[error("pulled image data is missing its manifest")]
+    MissingManifest,
+    /// No tag in the repository matched the requested version requirement
+    #[error("no version matching {0} found")]
+    NoMatchingVersion(semver::VersionReq),
+    /// Neither the image index nor the manifest contained an artifact for any of the requested
+    /// OSes. Contains the OSes that were requested
+    #[error("no artifact found for any of the requested platforms: {}", join_os(.0))]
+    NoMatchingPlatform(Vec<WasmOs>),
+    /// The config violates one or more rules of the OCI Wasm specification
+    #[error("invalid Wasm config: {}", join_violations(.0))]
+    InvalidConfig(Vec<ConfigViolation>),
+    /// The `SOURCE_DATE_EPOCH` environment variable was set but wasn't a valid Unix timestamp.
+    /// Contains the value that was found
+    #[error("SOURCE_DATE_EPOCH must be a Unix timestamp, found {0}")]
+    InvalidSourceDateEpoch(String),
+    /// The config could not be parsed as a [`WasmConfig`](crate::WasmConfig)
+    #[error("unable to parse Wasm config")]
+    ConfigParse(#[source] serde_json::Error),
+    /// The config could not be serialized to JSON
+    #[error("unable to serialize Wasm config")]
+    ConfigSerialize(#[source] serde_json::Error),
+    /// A manifest or image index could not be parsed
+    #[error("unable to parse manifest")]
+    ManifestParse(#[source] serde_json::Error),
+    /// A manifest or image index could not be serialized to JSON
+    #[error("unable to serialize manifest")]
+    ManifestSerialize(#[source] serde_json::Error),
+    /// An OCI image layout on disk was missing data or was otherwise invalid
+    #[error("invalid OCI image layout: {0}")]
+    InvalidLayout(String),
+    /// The bytes are not a wasm module or component
+    #[error("data is not a wasm module or component")]
+    NotWasm,
+    /// The bytes could not be decoded as a core wasm module
+    #[error("failed to decode wasm module")]
+    ModuleDecode(#[source] anyhow::Error),
+    /// The bytes could not be decoded as a component or WIT package
+    #[error("failed to decode WIT component")]
+    ComponentDecode(#[source] anyhow::Error),
+    /// The world could not be found in the resolve
+    #[error("component world not found")]
+    WorldNotFound,
+    /// The component doesn't conform to the world required by the client. Contains the report
+    /// listing what is missing or disallowed
+    #[error("component does not conform to world {0}")]
+    WorldConformance(WorldConformance),
+    /// A signing or verifying key could not be loaded or encoded. Contains a description of the
+    /// problem
+    #[error("invalid key: {0}")]
+    InvalidKey(String),
+    /// None of the signatures of a manifest or of the signature embedded in a layer could be
+    /// verified with the provided keys, or it wasn't signed at all. Contains the digest of the
+    /// manifest or layer
+    #[error("no valid signature found for {0}")]
+    NoValidSignature(String),
+    /// A signature layer sent by the registry was larger than any simple signing payload can be.
+    /// Contains the digest of the layer
+    #[error("signature payload {0} is too large")]
+    SignaturePayloadTooLarge(String),
+    /// The embedded signature of a layer was made by a trusted key other than the one recorded in
+    /// the `signerKeyId` field of its config
+    #[error("layer was signed by {actual}, but the config names {expected} as the signer")]
+    SignerMismatch {
+        /// The key id recorded in the config
+        expected: String,
+        /// The id of the trusted key that made the signature
+        actual: String,
+    },
+    /// The package could not be found in the resolve
+    #[error("package not found")]
+    PackageNotFound,
+    /// An I/O error occurred while reading or writing data
+    #[error("I/O error")]
+    Io(#[from] std::io::Error),
+    /// An error returned from the underlying OCI client
+    #[error(transparent)]
+    Oci(#[from] OciDistributionError),
+}
+
+/// A single rule of the OCI Wasm specification that a [`WasmConfig`](crate::WasmConfig) violates
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum ConfigViolation {
+    /// The architecture was not `wasm`. Contains the architecture that was found
+    #[error("architecture must be {WASM_ARCHITECTURE}, found {0}")]
+    WrongArchitecture(String),
+    /// The os was not one of the values defined by the specification. Contains the os that was
+    /// found
+    #[error("os must be {MODULE_OS}, {COMPONENT_OS} or {ASYNC_COMPONENT_OS}, found {0}")]
+    UnknownOs(String),
+    /// The os was a component OS such as `wasip2` but no component information was set. Contains
+    /// the os that was found
+    #[error("component information is required when os is {0}")]
+    MissingComponent(WasmOs),
+    /// No layer digests were listed
+    #[error("at least one layer digest is required")]
+    MissingLayerDigests,
+    /// A layer digest was not a well-formed sha256 digest. Contains the digest that was found
+    #[error("layer digest {0} is not a valid sha256 digest")]
+    MalformedLayerDigest(String),
+}
+
+fn join_os(os: &[WasmOs]) -> String {
+    os.iter().map(WasmOs::as_str).collect::<Vec<_>>().join(", ")
+}
+
+fn join_violations(violations: &[ConfigViolation]) -> String {
+    violations
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
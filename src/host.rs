@@ -0,0 +1,168 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use semver::{Comparator, Op, Version};
+use wit_parser::{Resolve, WorldId, WorldItem};
+
+use crate::{Component, Error, Result};
+
+/// A description of the interfaces and freestanding functions a host runtime provides to the
+/// components it runs, used to
+/// check whether a component can run on it with [`Component::check_host`] or
+/// [`WasmConfig::check_host`](crate::WasmConfig::check_host)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostCapabilities {
+    /// Maps each provided interface name without its version to all versions provided
+    interfaces: BTreeMap<String, BTreeSet<Option<Version>>>,
+    /// The names of the functions provided directly to a component's world
+    functions: BTreeSet<String>,
+}
+
+impl HostCapabilities {
+    /// Creates a description of a host that provides the given interfaces. Names are in the same
+    /// format as [`Component::imports`], e.g. `wasi:http/types@0.2.0`
+    pub fn new<I, S>(interfaces: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut capabilities = Self::default();
+        for name in interfaces {
+            let (name, version) = split_version(name.as_ref());
+            capabilities
+                .interfaces
+                .entry(name.to_string())
+                .or_default()
+                .insert(version);
+        }
+        capabilities
+    }
+
+    /// Adds the given functions to the ones this host provides directly to a component's world
+    /// rather than through an interface. Names are in the same format as
+    /// [`Component::imported_functions`]
+    #[must_use]
+    pub fn with_functions<I, S>(mut self, functions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.functions.extend(functions.into_iter().map(Into::into));
+        self
+    }
+
+    /// Creates a description of a host that provides every interface and function imported by the
+    /// given world
+    ///
+    /// Returns an error only if the world doesn't exist in the resolve.
+    pub fn from_world(resolve: &Resolve, world_id: WorldId) -> Result<Self> {
+        let world = resolve.worlds.get(world_id).ok_or(Error::WorldNotFound)?;
+        let names = |matches: fn(&WorldItem) -> bool| {
+            world
+                .imports
+                .iter()
+                .filter(move |(_, item)| matches(item))
+                .map(|(key, _)| resolve.name_world_key(key))
+        };
+        Ok(
+            Self::new(names(|item| matches!(item, WorldItem::Interface { .. })))
+                .with_functions(names(|item| matches!(item, WorldItem::Function(_)))),
+        )
+    }
+
+    /// Returns true if this host provides an interface that satisfies the given import. Versioned
+    /// imports are satisfied by any provided version that is semver compatible and at least as
+    /// new, following the same rules as WIT: versions with the same non-zero major version are
+    /// compatible, as are `0.x` versions with the same non-zero minor version. Pre-release
+    /// versions and `0.0.x` versions must match exactly
+    pub fn provides(&self, import: &str) -> bool {
+        let (name, version) = split_version(import);
+        let Some(provided) = self.interfaces.get(name) else {
+            return false;
+        };
+        match version {
+            None => provided.contains(&None),
+            Some(version) => provided
+                .iter()
+                .flatten()
+                .any(|provided| is_compatible(&version, provided)),
+        }
+    }
+
+    /// Returns true if this host provides a function with the given name directly to a
+    /// component's world
+    pub fn provides_function(&self, function: &str) -> bool {
+        self.functions.contains(function)
+    }
+}
+
+/// The result of checking a component against a host with [`Component::check_host`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostCompatibility {
+    /// Imported interfaces the host doesn't provide a compatible version of, followed by imported
+    /// functions the host doesn't provide
+    pub unsatisfied_imports: Vec<String>,
+}
+
+impl HostCompatibility {
+    /// Returns true if the host provides every import of the component
+    pub fn is_compatible(&self) -> bool {
+        self.unsatisfied_imports.is_empty()
+    }
+}
+
+impl Component {
+    /// Checks whether every interface and function imported by this component is provided by the
+    /// given host. This only needs the component information from the config, so it can be used
+    /// with [`WasmClient::pull_manifest_and_config`](crate::WasmClient::pull_manifest_and_config)
+    /// without downloading the layer
+    pub fn check_host(&self, host: &HostCapabilities) -> HostCompatibility {
+        let interfaces = self.imports.iter().filter(|import| !host.provides(import));
+        let functions = self
+            .imported_functions
+            .iter()
+            .filter(|function| !host.provides_function(function));
+        HostCompatibility {
+            unsatisfied_imports: interfaces.chain(functions).cloned().collect(),
+        }
+    }
+}
+
+/// Splits a fully qualified interface name such as `wasi:cli/environment@0.2.0` into the name
+/// without its version and the parsed version. Names without a valid version are returned whole
+pub(crate) fn split_version(name: &str) -> (&str, Option<Version>) {
+    name.rsplit_once('@')
+        .and_then(|(base, version)| Some((base, Some(Version::parse(version).ok()?))))
+        .unwrap_or((name, None))
+}
+
+/// Returns true if the interface named `provided` can be used where the interface named
+/// `required` is expected: both must be the same interface and the provided version must be
+/// semver compatible and at least as new, using the same rules as [`HostCapabilities::provides`].
+/// For example `wasi:http/types@0.2.3` satisfies `wasi:http/types@0.2.0`, but not the other way
+/// around. Names without a version only match names without a version
+pub(crate) fn satisfies_interface(provided: &str, required: &str) -> bool {
+    let (provided_name, provided_version) = split_version(provided);
+    let (required_name, required_version) = split_version(required);
+    if provided_name != required_name {
+        return false;
+    }
+    match (provided_version, required_version) {
+        (None, None) => true,
+        (Some(provided), Some(required)) => is_compatible(&required, &provided),
+        _ => false,
+    }
+}
+
+fn is_compatible(required: &Version, provided: &Version) -> bool {
+    if !required.pre.is_empty() || !provided.pre.is_empty() {
+        return required == provided;
+    }
+    Comparator {
+        op: Op::Caret,
+        major: required.major,
+        minor: Some(required.minor),
+        patch: Some(required.patch),
+        pre: required.pre.clone(),
+    }
+    .matches(provided)
+}
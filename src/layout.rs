@@ -0,0 +1,295 @@
+use std::path::Path;
+
+use oci_client::{
+    annotations::ORG_OPENCONTAINERS_IMAGE_REF_NAME,
+    client::{Config, ImageData, ImageLayer},
+    manifest::{ImageIndexEntry, OciImageIndex, OciImageManifest, OCI_IMAGE_INDEX_MEDIA_TYPE},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::{build_manifest, canonical_json, validate_layer_media_type, validate_manifest},
+    config::{is_sha256_digest, sha256_digest},
+    Error, Result, WasmConfig, WASM_LAYER_MEDIA_TYPE, WASM_MANIFEST_MEDIA_TYPE,
+};
+
+/// The name of the file marking the root of an OCI image layout
+pub(crate) const OCI_LAYOUT_FILE: &str = "oci-layout";
+/// The name of the image index file at the root of an OCI image layout
+pub(crate) const INDEX_FILE: &str = "index.json";
+const IMAGE_LAYOUT_VERSION: &str = "1.0.0";
+
+/// A file in an image layout, as a path relative to the root of the layout and its contents
+pub(crate) type LayoutFile = (String, Vec<u8>);
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OciLayout {
+    image_layout_version: String,
+}
+
+/// Writes the given wasm artifact into an [OCI image
+/// layout](https://github.com/opencontainers/image-spec/blob/main/image-layout.md) directory,
+/// creating the directory if it doesn't exist. If the directory already contains a layout, the
+/// artifact is added to its `index.json`, replacing any entry with the same `ref_name`.
+///
+/// The manifest is written as canonical JSON, the same way
+/// [`WasmClient::push`](crate::WasmClient::push) sends it, so the returned digest matches the
+/// digest the artifact has in a registry when it was pushed by this crate. To keep the exact
+/// manifest of an artifact pushed by other tools, use
+/// [`WasmClient::pull_to_layout`](crate::WasmClient::pull_to_layout) instead.
+///
+/// The artifact is validated with the same rules as [`WasmClient::pull`](crate::WasmClient::pull)
+/// before anything is written. Returns the digest of the manifest as written to the layout
+pub async fn write_image_layout(
+    dir: impl AsRef<Path>,
+    image_data: &ImageData,
+    ref_name: Option<&str>,
+) -> Result<String> {
+    write_layout(dir.as_ref(), image_data, None, ref_name).await
+}
+
+/// Writes the artifact into the layout, using the given raw manifest bytes as is if set
+pub(crate) async fn write_layout(
+    dir: &Path,
+    image_data: &ImageData,
+    manifest_data: Option<Vec<u8>>,
+    ref_name: Option<&str>,
+) -> Result<String> {
+    let index = match tokio::fs::read(dir.join(INDEX_FILE)).await {
+        Ok(data) => Some(serde_json::from_slice(&data).map_err(Error::ManifestParse)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    let (files, digest) = layout_files(image_data, manifest_data, ref_name, index)?;
+    for (path, data) in files {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, data).await?;
+    }
+    Ok(digest)
+}
+
+/// Reads a wasm artifact from an [OCI image
+/// layout](https://github.com/opencontainers/image-spec/blob/main/image-layout.md) directory. If
+/// `ref_name` is set, the manifest annotated with that `org.opencontainers.image.ref.name` is
+/// loaded, otherwise the layout must contain exactly one manifest.
+///
+/// All blobs are verified against their digests and the artifact is validated with the same rules
+/// as [`WasmClient::pull_and_verify`](crate::WasmClient::pull_and_verify)
+pub async fn read_image_layout(
+    dir: impl AsRef<Path>,
+    ref_name: Option<&str>,
+) -> Result<(ImageData, WasmConfig)> {
+    let dir = dir.as_ref();
+    let layout = read_layout_file(dir, OCI_LAYOUT_FILE).await?;
+    check_layout_version(&layout)?;
+    let index = read_layout_file(dir, INDEX_FILE).await?;
+    let entry = select_manifest(&index, ref_name)?;
+    let manifest = read_layout_file(dir, &blob_path(&entry.digest)?).await?;
+    let manifest = parse_manifest(&manifest, &entry.digest)?;
+    let config = read_layout_file(dir, &blob_path(&manifest.config.digest)?).await?;
+    let layer = read_layout_file(dir, &blob_path(&manifest.layers[0].digest)?).await?;
+    build_image_data(manifest, entry.digest, config, layer)
+}
+
+async fn read_layout_file(dir: &Path, path: &str) -> Result<Vec<u8>> {
+    match tokio::fs::read(dir.join(path)).await {
+        Ok(data) => Ok(data),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Err(Error::InvalidLayout(format!("missing {path}")))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Generates all files making up an image layout for the given artifact. If an existing index is
+/// given, the artifact is added to it. The raw manifest bytes are written as is if given, otherwise
+/// the manifest is serialized as canonical JSON. Also returns the digest of the manifest
+pub(crate) fn layout_files(
+    image_data: &ImageData,
+    manifest_data: Option<Vec<u8>>,
+    ref_name: Option<&str>,
+    index: Option<OciImageIndex>,
+) -> Result<(Vec<LayoutFile>, String)> {
+    let [layer] = image_data.layers.as_slice() else {
+        return Err(Error::WrongLayerCount(image_data.layers.len()));
+    };
+    let (manifest, manifest_data) = match manifest_data {
+        Some(data) => (
+            serde_json::from_slice::<OciImageManifest>(&data).map_err(Error::ManifestParse)?,
+            data,
+        ),
+        None => {
+            let manifest = match image_data.manifest.clone() {
+                Some(manifest) => manifest,
+                None => build_manifest(std::slice::from_ref(layer), &image_data.config, None),
+            };
+            let data = canonical_json(&manifest)?;
+            (manifest, data)
+        }
+    };
+    validate_manifest(&manifest)?;
+    validate_layer_media_type(&layer.media_type)?;
+    verify_blob(&image_data.config.data, &manifest.config.digest)?;
+    let layer_digest = layer.sha256_digest();
+    if layer_digest != manifest.layers[0].digest {
+        return Err(Error::LayerDigestMismatch {
+            expected: manifest.layers[0].digest.clone(),
+            actual: layer_digest,
+        });
+    }
+
+    let manifest_digest = sha256_digest(&manifest_data);
+
+    let mut index = index.unwrap_or_else(|| OciImageIndex {
+        schema_version: 2,
+        media_type: Some(OCI_IMAGE_INDEX_MEDIA_TYPE.to_string()),
+        manifests: Vec::new(),
+        artifact_type: None,
+        annotations: None,
+    });
+    index.manifests.retain(|entry| {
+        entry.digest != manifest_digest && (ref_name.is_none() || entry_ref_name(entry) != ref_name)
+    });
+    index.manifests.push(ImageIndexEntry {
+        media_type: WASM_MANIFEST_MEDIA_TYPE.to_string(),
+        digest: manifest_digest.clone(),
+        size: manifest_data.len() as i64,
+        platform: None,
+        annotations: ref_name.map(|name| {
+            [(
+                ORG_OPENCONTAINERS_IMAGE_REF_NAME.to_string(),
+                name.to_string(),
+            )]
+            .into()
+        }),
+    });
+
+    let layout = OciLayout {
+        image_layout_version: IMAGE_LAYOUT_VERSION.to_string(),
+    };
+    let files = vec![
+        (
+            OCI_LAYOUT_FILE.to_string(),
+            serde_json::to_vec(&layout).map_err(Error::ManifestSerialize)?,
+        ),
+        (blob_path(&layer_digest)?, layer.data.to_vec()),
+        (
+            blob_path(&manifest.config.digest)?,
+            image_data.config.data.to_vec(),
+        ),
+        (blob_path(&manifest_digest)?, manifest_data),
+        (
+            INDEX_FILE.to_string(),
+            serde_json::to_vec(&index).map_err(Error::ManifestSerialize)?,
+        ),
+    ];
+    Ok((files, manifest_digest))
+}
+
+/// Checks that the contents of an `oci-layout` file describe a supported layout version
+pub(crate) fn check_layout_version(data: &[u8]) -> Result<()> {
+    let layout: OciLayout = serde_json::from_slice(data)
+        .map_err(|e| Error::InvalidLayout(format!("unable to parse {OCI_LAYOUT_FILE}: {e}")))?;
+    if layout.image_layout_version != IMAGE_LAYOUT_VERSION {
+        return Err(Error::InvalidLayout(format!(
+            "unsupported image layout version {}",
+            layout.image_layout_version
+        )));
+    }
+    Ok(())
+}
+
+/// Selects the manifest to load from the raw contents of an `index.json` file
+pub(crate) fn select_manifest(index: &[u8], ref_name: Option<&str>) -> Result<ImageIndexEntry> {
+    let index: OciImageIndex = serde_json::from_slice(index).map_err(Error::ManifestParse)?;
+    let mut manifests = index
+        .manifests
+        .into_iter()
+        .filter(|entry| entry.media_type == WASM_MANIFEST_MEDIA_TYPE);
+    match ref_name {
+        Some(name) => manifests
+            .find(|entry| entry_ref_name(entry) == Some(name))
+            .ok_or_else(|| Error::InvalidLayout(format!("no manifest found with name {name}"))),
+        None => match (manifests.next(), manifests.next()) {
+            (Some(entry), None) => Ok(entry),
+            (None, _) => Err(Error::InvalidLayout("no manifests found".to_string())),
+            (Some(_), Some(_)) => Err(Error::InvalidLayout(
+                "multiple manifests found, a reference name is required".to_string(),
+            )),
+        },
+    }
+}
+
+/// Verifies and parses the raw manifest, checking that it is a valid wasm manifest
+pub(crate) fn parse_manifest(data: &[u8], digest: &str) -> Result<OciImageManifest> {
+    verify_blob(data, digest)?;
+    let manifest: OciImageManifest = serde_json::from_slice(data).map_err(Error::ManifestParse)?;
+    validate_manifest(&manifest)?;
+    validate_layer_media_type(&manifest.layers[0].media_type)?;
+    Ok(manifest)
+}
+
+/// Verifies the config and layer blobs against the manifest and assembles them into an
+/// [`ImageData`]
+pub(crate) fn build_image_data(
+    manifest: OciImageManifest,
+    digest: String,
+    config: Vec<u8>,
+    layer: Vec<u8>,
+) -> Result<(ImageData, WasmConfig)> {
+    verify_blob(&config, &manifest.config.digest)?;
+    let actual = sha256_digest(&layer);
+    if actual != manifest.layers[0].digest {
+        return Err(Error::LayerDigestMismatch {
+            expected: manifest.layers[0].digest.clone(),
+            actual,
+        });
+    }
+    let wasm_config = WasmConfig::parse_strict(&config)?;
+    wasm_config.verify_layer_digest(&actual)?;
+
+    let image_data = ImageData {
+        layers: vec![ImageLayer::new(
+            layer,
+            WASM_LAYER_MEDIA_TYPE.to_string(),
+            manifest.layers[0].annotations.clone(),
+        )],
+        digest: Some(digest),
+        config: Config::new(
+            config,
+            manifest.config.media_type.clone(),
+            manifest.annotations.clone(),
+        ),
+        manifest: Some(manifest),
+    };
+    Ok((image_data, wasm_config))
+}
+
+/// Returns the path of the blob with the given digest, relative to the root of the layout
+pub(crate) fn blob_path(digest: &str) -> Result<String> {
+    match digest.strip_prefix("sha256:") {
+        Some(encoded) if is_sha256_digest(digest) => Ok(format!("blobs/sha256/{encoded}")),
+        _ => Err(Error::InvalidLayout(format!("unsupported digest {digest}"))),
+    }
+}
+
+fn verify_blob(data: &[u8], digest: &str) -> Result<()> {
+    if sha256_digest(data) != digest {
+        return Err(Error::InvalidLayout(format!(
+            "blob does not match digest {digest}"
+        )));
+    }
+    Ok(())
+}
+
+fn entry_ref_name(entry: &ImageIndexEntry) -> Option<&str> {
+    entry
+        .annotations
+        .as_ref()?
+        .get(ORG_OPENCONTAINERS_IMAGE_REF_NAME)
+        .map(String::as_str)
+}
@@ -1,10 +1,38 @@
+mod archive;
+mod cache;
 mod client;
 mod component;
 mod config;
+mod embedded_signature;
+mod error;
+mod host;
+mod layout;
+mod metadata;
+mod module;
+mod os;
+mod platform;
+mod referrers;
+mod signature;
+mod version;
 
+pub use archive::{read_image_archive, write_image_archive};
+pub use cache::{Cache, CacheConfig};
 pub use client::WasmClient;
-pub use component::Component;
-pub use config::{AnnotatedWasmConfig, ToConfig, WasmConfig};
+pub use component::{Component, WasiVersionRange, WasmKind, WorldConformance};
+pub use config::{AnnotatedWasmConfig, ToConfig, WasmConfig, WasmConfigBuilder};
+pub use error::{ConfigViolation, Error, Result};
+pub use host::{HostCapabilities, HostCompatibility};
+pub use layout::{read_image_layout, write_image_layout};
+pub use metadata::{ComponentMetadata, FunctionMetadata, InterfaceMetadata, TypeMetadata};
+pub use module::{Module, ModuleExport, ModuleImport, ModuleItemKind, WasiSnapshot};
+pub use os::WasmOs;
+pub use platform::{
+    wasm_platform_resolver, wasm_platform_resolver_with_preference, PlatformResolver,
+    DEFAULT_OS_PREFERENCE,
+};
+pub use referrers::Referrer;
+pub use signature::{SigningKey, VerifyingKey};
+pub use version::resolve_tag;
 
 pub const WASM_MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
 pub const WASM_MANIFEST_CONFIG_MEDIA_TYPE: &str = "application/vnd.wasm.config.v0+json";
@@ -12,3 +40,4 @@ pub const WASM_LAYER_MEDIA_TYPE: &str = "application/wasm";
 pub const WASM_ARCHITECTURE: &str = "wasm";
 pub const MODULE_OS: &str = "wasip1";
 pub const COMPONENT_OS: &str = "wasip2";
+pub const ASYNC_COMPONENT_OS: &str = "wasip3";
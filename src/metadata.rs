@@ -0,0 +1,250 @@
+use serde::{Deserialize, Serialize};
+use wit_parser::{
+    Function, Handle, InterfaceId, PackageId, Resolve, Type, TypeDefKind, TypeId, WorldId,
+    WorldItem, WorldKey,
+};
+
+use crate::{Error, Result};
+
+/// Detailed metadata about the interfaces, functions and types a component imports and exports,
+/// each kept in a separate list so they can be told apart even when they share a name. This
+/// is optional and only generated when requested with
+/// [`Component::from_raw_component_with_metadata`](crate::Component::from_raw_component_with_metadata)
+/// or [`ComponentMetadata::from_world`], as it can be large for components with many imports
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ComponentMetadata {
+    /// All interfaces imported by the component, sorted by name
+    pub imports: Vec<InterfaceMetadata>,
+    /// All interfaces exported by the component, sorted by name
+    pub exports: Vec<InterfaceMetadata>,
+    /// Functions imported directly by the component's world rather than through an interface,
+    /// sorted by name
+    pub imported_functions: Vec<FunctionMetadata>,
+    /// Functions exported directly by the component's world rather than through an interface,
+    /// sorted by name
+    pub exported_functions: Vec<FunctionMetadata>,
+    /// Types imported directly by the component's world, either defined in the world or pulled
+    /// in from an interface with `use`, sorted by name
+    pub imported_types: Vec<TypeMetadata>,
+    /// Types exported directly by the component's world, sorted by name
+    pub exported_types: Vec<TypeMetadata>,
+}
+
+/// Metadata about a single imported or exported interface
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct InterfaceMetadata {
+    /// The name of the interface in the same format as [`Component::imports`](crate::Component)
+    /// and [`Component::exports`](crate::Component), e.g. `wasi:http/types@0.2.0`
+    pub name: String,
+    /// All functions in the interface, including resource methods, in the order they are defined
+    pub functions: Vec<FunctionMetadata>,
+    /// The names of all resource types defined by the interface, in the order they are defined
+    pub resources: Vec<String>,
+}
+
+/// Metadata about a single function
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FunctionMetadata {
+    /// The name of the function as it appears in the component binary. Resource methods,
+    /// constructors and static functions use the `[method]resource.name` style prefixes
+    pub name: String,
+    /// The signature of the function in WIT syntax, e.g. `func(name: string) -> option<u32>`
+    pub signature: String,
+}
+
+/// Metadata about a single type
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TypeMetadata {
+    /// The name of the type
+    pub name: String,
+    /// The kind of the type, such as `record`, `variant`, `enum`, `flags` or `resource`. Aliases
+    /// are followed to the type they refer to, and aliases of primitive types have the kind `type`
+    pub kind: String,
+}
+
+impl ComponentMetadata {
+    /// Generates metadata for everything imported and exported by the given world
+    ///
+    /// Returns an error only if the world doesn't exist in the resolve.
+    pub fn from_world(resolve: &Resolve, world_id: WorldId) -> Result<Self> {
+        let world = resolve.worlds.get(world_id).ok_or(Error::WorldNotFound)?;
+        let mut metadata = ComponentMetadata::default();
+        for (key, item) in world.imports.iter() {
+            match item {
+                WorldItem::Interface { id, .. } => {
+                    metadata.imports.push(interface_metadata(resolve, key, *id))
+                }
+                WorldItem::Function(func) => metadata
+                    .imported_functions
+                    .push(function_metadata(resolve, func)),
+                WorldItem::Type(id) => metadata
+                    .imported_types
+                    .push(type_metadata(resolve, key, *id)),
+            }
+        }
+        for (key, item) in world.exports.iter() {
+            match item {
+                WorldItem::Interface { id, .. } => {
+                    metadata.exports.push(interface_metadata(resolve, key, *id))
+                }
+                WorldItem::Function(func) => metadata
+                    .exported_functions
+                    .push(function_metadata(resolve, func)),
+                WorldItem::Type(id) => metadata
+                    .exported_types
+                    .push(type_metadata(resolve, key, *id)),
+            }
+        }
+        metadata.sort();
+        Ok(metadata)
+    }
+
+    /// Generates metadata for a WIT package. Every interface defined by the package is listed as
+    /// an export, matching [`Component::from_package`](crate::Component::from_package)
+    ///
+    /// Returns an error only if the package doesn't exist in the resolve.
+    pub fn from_package(resolve: &Resolve, pkg_id: PackageId) -> Result<Self> {
+        let pkg = resolve.packages.get(pkg_id).ok_or(Error::PackageNotFound)?;
+        let mut metadata = ComponentMetadata {
+            exports: pkg
+                .interfaces
+                .values()
+                .map(|id| interface_metadata(resolve, &WorldKey::Interface(*id), *id))
+                .collect(),
+            ..Default::default()
+        };
+        metadata.sort();
+        Ok(metadata)
+    }
+
+    fn sort(&mut self) {
+        self.imports.sort_by(|a, b| a.name.cmp(&b.name));
+        self.exports.sort_by(|a, b| a.name.cmp(&b.name));
+        self.imported_functions.sort_by(|a, b| a.name.cmp(&b.name));
+        self.exported_functions.sort_by(|a, b| a.name.cmp(&b.name));
+        self.imported_types.sort_by(|a, b| a.name.cmp(&b.name));
+        self.exported_types.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+}
+
+fn interface_metadata(resolve: &Resolve, key: &WorldKey, id: InterfaceId) -> InterfaceMetadata {
+    let interface = &resolve.interfaces[id];
+    InterfaceMetadata {
+        name: resolve.name_world_key(key),
+        functions: interface
+            .functions
+            .values()
+            .map(|func| function_metadata(resolve, func))
+            .collect(),
+        resources: interface
+            .types
+            .iter()
+            .filter(|(_, ty)| matches!(resolve.types[**ty].kind, TypeDefKind::Resource))
+            .map(|(name, _)| name.clone())
+            .collect(),
+    }
+}
+
+fn function_metadata(resolve: &Resolve, func: &Function) -> FunctionMetadata {
+    let params = func
+        .params
+        .iter()
+        .map(|(name, ty)| format!("{name}: {}", type_name(resolve, ty)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut signature = if func.kind.is_async() {
+        format!("async func({params})")
+    } else {
+        format!("func({params})")
+    };
+    if let Some(result) = &func.result {
+        signature.push_str(" -> ");
+        signature.push_str(&type_name(resolve, result));
+    }
+    FunctionMetadata {
+        name: func.name.clone(),
+        signature,
+    }
+}
+
+fn type_metadata(resolve: &Resolve, key: &WorldKey, mut id: TypeId) -> TypeMetadata {
+    while let TypeDefKind::Type(Type::Id(aliased)) = resolve.types[id].kind {
+        id = aliased;
+    }
+    TypeMetadata {
+        name: resolve.name_world_key(key),
+        kind: resolve.types[id].kind.as_str().to_string(),
+    }
+}
+
+/// Renders a type in WIT syntax. Named types are rendered by name
+fn type_name(resolve: &Resolve, ty: &Type) -> String {
+    let id = match ty {
+        Type::Bool => return "bool".to_string(),
+        Type::U8 => return "u8".to_string(),
+        Type::U16 => return "u16".to_string(),
+        Type::U32 => return "u32".to_string(),
+        Type::U64 => return "u64".to_string(),
+        Type::S8 => return "s8".to_string(),
+        Type::S16 => return "s16".to_string(),
+        Type::S32 => return "s32".to_string(),
+        Type::S64 => return "s64".to_string(),
+        Type::F32 => return "f32".to_string(),
+        Type::F64 => return "f64".to_string(),
+        Type::Char => return "char".to_string(),
+        Type::String => return "string".to_string(),
+        Type::ErrorContext => return "error-context".to_string(),
+        Type::Id(id) => *id,
+    };
+    let def = &resolve.types[id];
+    if let Some(name) = &def.name {
+        return name.clone();
+    }
+    let optional = |ty: &Option<Type>| match ty {
+        Some(ty) => type_name(resolve, ty),
+        None => "_".to_string(),
+    };
+    match &def.kind {
+        TypeDefKind::Handle(Handle::Own(id)) => type_name(resolve, &Type::Id(*id)),
+        TypeDefKind::Handle(Handle::Borrow(id)) => {
+            format!("borrow<{}>", type_name(resolve, &Type::Id(*id)))
+        }
+        TypeDefKind::Tuple(tuple) => format!(
+            "tuple<{}>",
+            tuple
+                .types
+                .iter()
+                .map(|ty| type_name(resolve, ty))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        TypeDefKind::Option(ty) => format!("option<{}>", type_name(resolve, ty)),
+        TypeDefKind::Result(result) => match (&result.ok, &result.err) {
+            (None, None) => "result".to_string(),
+            (Some(ok), None) => format!("result<{}>", type_name(resolve, ok)),
+            (ok, Some(err)) => format!("result<{}, {}>", optional(ok), type_name(resolve, err)),
+        },
+        TypeDefKind::List(ty) => format!("list<{}>", type_name(resolve, ty)),
+        TypeDefKind::Map(key, value) => format!(
+            "map<{}, {}>",
+            type_name(resolve, key),
+            type_name(resolve, value)
+        ),
+        TypeDefKind::FixedSizeList(ty, size) => format!("list<{}, {size}>", type_name(resolve, ty)),
+        TypeDefKind::Future(ty) => match ty {
+            Some(ty) => format!("future<{}>", type_name(resolve, ty)),
+            None => "future".to_string(),
+        },
+        TypeDefKind::Stream(ty) => match ty {
+            Some(ty) => format!("stream<{}>", type_name(resolve, ty)),
+            None => "stream".to_string(),
+        },
+        TypeDefKind::Type(ty) => type_name(resolve, ty),
+        // Records, variants, enums, flags and resources are always named in valid WIT
+        kind => kind.as_str().to_string(),
+    }
+}
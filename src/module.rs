@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+use wasmparser::{Encoding, ExternalKind, Parser, Payload, TypeRef};
+
+use crate::{Error, Result};
+
+/// The module name used for imports from WASI preview 1
+const WASI_SNAPSHOT_PREVIEW1: &str = "wasi_snapshot_preview1";
+/// The module name used for imports from the WASI snapshot that preceded preview 1
+const WASI_UNSTABLE: &str = "wasi_unstable";
+
+/// Information about a plain wasm module, synthesized from its imports and exports. This isn't
+/// part of the OCI Wasm specification and is stored in the optional `module` field of a
+/// [`WasmConfig`](crate::WasmConfig) so modules can be indexed like components
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Module {
+    /// All imports of the module, in the order they appear in the binary
+    pub imports: Vec<ModuleImport>,
+    /// All exports of the module, in the order they appear in the binary
+    pub exports: Vec<ModuleExport>,
+    /// The WASI snapshot the module imports from, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wasi: Option<WasiSnapshot>,
+}
+
+/// A single import of a [`Module`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ModuleImport {
+    /// The name of the module the item is imported from, e.g. `wasi_snapshot_preview1`
+    pub module: String,
+    /// The name of the imported item
+    pub name: String,
+    /// The kind of the imported item
+    pub kind: ModuleItemKind,
+}
+
+/// A single export of a [`Module`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ModuleExport {
+    /// The name of the exported item
+    pub name: String,
+    /// The kind of the exported item
+    pub kind: ModuleItemKind,
+}
+
+/// The kind of an item imported or exported by a [`Module`]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ModuleItemKind {
+    /// A function
+    Func,
+    /// A table
+    Table,
+    /// A linear memory
+    Memory,
+    /// A global
+    Global,
+    /// An exception handling tag
+    Tag,
+}
+
+/// The WASI snapshot a plain wasm module targets, detected from the module names of its imports
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WasiSnapshot {
+    /// WASI preview 1 (`wasi_snapshot_preview1`)
+    #[serde(rename = "wasi_snapshot_preview1")]
+    Preview1,
+    /// The snapshot that preceded preview 1 (`wasi_unstable`)
+    #[serde(rename = "wasi_unstable")]
+    Unstable,
+}
+
+impl Module {
+    /// Create a module by loading the given module from the filesystem
+    pub async fn from_module(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let data = tokio::fs::read(path).await?;
+        Self::from_raw_module(data)
+    }
+
+    /// Create a module from the raw bytes of a core wasm module. If the module imports from both
+    /// `wasi_snapshot_preview1` and `wasi_unstable`, it is reported as targeting preview 1
+    ///
+    /// Returns [`Error::ModuleDecode`] if the bytes aren't a valid core module
+    pub fn from_raw_module(raw: impl AsRef<[u8]>) -> Result<Self> {
+        let mut module = Module::default();
+        for payload in Parser::new(0).parse_all(raw.as_ref()) {
+            match payload.map_err(|e| Error::ModuleDecode(e.into()))? {
+                Payload::Version {
+                    encoding: Encoding::Component,
+                    ..
+                } => {
+                    return Err(Error::ModuleDecode(anyhow::anyhow!(
+                        "expected a core module, found a component"
+                    )));
+                }
+                Payload::ImportSection(reader) => {
+                    for import in reader.into_imports() {
+                        let import = import.map_err(|e| Error::ModuleDecode(e.into()))?;
+                        module.imports.push(ModuleImport {
+                            module: import.module.to_string(),
+                            name: import.name.to_string(),
+                            kind: import_kind(&import.ty),
+                        });
+                    }
+                }
+                Payload::ExportSection(reader) => {
+                    for export in reader {
+                        let export = export.map_err(|e| Error::ModuleDecode(e.into()))?;
+                        module.exports.push(ModuleExport {
+                            name: export.name.to_string(),
+                            kind: export_kind(export.kind),
+                        });
+                    }
+                }
+                // Imports and exports always come before the code
+                Payload::CodeSectionStart { .. } => break,
+                _ => {}
+            }
+        }
+
+        let imports_from = |name: &str| module.imports.iter().any(|i| i.module == name);
+        module.wasi = if imports_from(WASI_SNAPSHOT_PREVIEW1) {
+            Some(WasiSnapshot::Preview1)
+        } else if imports_from(WASI_UNSTABLE) {
+            Some(WasiSnapshot::Unstable)
+        } else {
+            None
+        };
+        Ok(module)
+    }
+}
+
+fn import_kind(ty: &TypeRef) -> ModuleItemKind {
+    match ty {
+        TypeRef::Func(_) | TypeRef::FuncExact(_) => ModuleItemKind::Func,
+        TypeRef::Table(_) => ModuleItemKind::Table,
+        TypeRef::Memory(_) => ModuleItemKind::Memory,
+        TypeRef::Global(_) => ModuleItemKind::Global,
+        TypeRef::Tag(_) => ModuleItemKind::Tag,
+    }
+}
+
+fn export_kind(kind: ExternalKind) -> ModuleItemKind {
+    match kind {
+        ExternalKind::Func | ExternalKind::FuncExact => ModuleItemKind::Func,
+        ExternalKind::Table => ModuleItemKind::Table,
+        ExternalKind::Memory => ModuleItemKind::Memory,
+        ExternalKind::Global => ModuleItemKind::Global,
+        ExternalKind::Tag => ModuleItemKind::Tag,
+    }
+}
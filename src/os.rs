@@ -0,0 +1,95 @@
+use std::{convert::Infallible, fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ASYNC_COMPONENT_OS, COMPONENT_OS, MODULE_OS};
+
+/// The OS (i.e. the WASI version) targeted by a wasm artifact, as stored in the `os` field of a
+/// [`WasmConfig`](crate::WasmConfig). This serializes to and from the plain string values defined
+/// by the OCI Wasm specification. Values this crate doesn't know about are preserved as
+/// [`WasmOs::Unknown`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum WasmOs {
+    /// A core module targeting WASI preview 1 (`wasip1`)
+    Wasip1,
+    /// A component targeting WASI preview 2 (`wasip2`)
+    Wasip2,
+    /// A component targeting WASI preview 3 (`wasip3`)
+    Wasip3,
+    /// Any other OS value
+    Unknown(String),
+}
+
+impl WasmOs {
+    /// Returns the string value of this OS as used in the config
+    pub fn as_str(&self) -> &str {
+        match self {
+            WasmOs::Wasip1 => MODULE_OS,
+            WasmOs::Wasip2 => COMPONENT_OS,
+            WasmOs::Wasip3 => ASYNC_COMPONENT_OS,
+            WasmOs::Unknown(os) => os,
+        }
+    }
+
+    /// Returns true if artifacts with this OS are components, which means the config must contain
+    /// component information
+    pub fn is_component(&self) -> bool {
+        matches!(self, WasmOs::Wasip2 | WasmOs::Wasip3)
+    }
+}
+
+impl fmt::Display for WasmOs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<&str> for WasmOs {
+    fn from(value: &str) -> Self {
+        match value {
+            MODULE_OS => WasmOs::Wasip1,
+            COMPONENT_OS => WasmOs::Wasip2,
+            ASYNC_COMPONENT_OS => WasmOs::Wasip3,
+            other => WasmOs::Unknown(other.to_string()),
+        }
+    }
+}
+
+impl From<String> for WasmOs {
+    fn from(value: String) -> Self {
+        match WasmOs::from(value.as_str()) {
+            WasmOs::Unknown(_) => WasmOs::Unknown(value),
+            os => os,
+        }
+    }
+}
+
+impl From<WasmOs> for String {
+    fn from(value: WasmOs) -> Self {
+        match value {
+            WasmOs::Unknown(os) => os,
+            os => os.as_str().to_string(),
+        }
+    }
+}
+
+impl FromStr for WasmOs {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(WasmOs::from(s))
+    }
+}
+
+impl PartialEq<str> for WasmOs {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for WasmOs {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
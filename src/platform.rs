@@ -0,0 +1,45 @@
+use oci_client::manifest::ImageIndexEntry;
+use oci_spec::image::{Arch, Os};
+
+use crate::{WasmOs, WASM_ARCHITECTURE};
+
+/// A boxed platform resolver, as accepted by
+/// [`ClientConfig::platform_resolver`](oci_client::client::ClientConfig)
+pub type PlatformResolver = Box<dyn Fn(&[ImageIndexEntry]) -> Option<String> + Send + Sync>;
+
+/// The OSes preferred by [`wasm_platform_resolver`], in order
+pub const DEFAULT_OS_PREFERENCE: &[WasmOs] = &[WasmOs::Wasip2, WasmOs::Wasip1];
+
+/// A platform resolver for [`ClientConfig::platform_resolver`](oci_client::client::ClientConfig)
+/// that selects the wasm entry of an image index, preferring `wasm/wasip2` over `wasm/wasip1`.
+/// This is what [`WasmClient::new_with_defaults`](crate::WasmClient::new_with_defaults) uses.
+/// Use [`wasm_platform_resolver_with_preference`] for a different preference order
+pub fn wasm_platform_resolver(manifests: &[ImageIndexEntry]) -> Option<String> {
+    select_platform(manifests, DEFAULT_OS_PREFERENCE).map(|entry| entry.digest.clone())
+}
+
+/// Creates a platform resolver for
+/// [`ClientConfig::platform_resolver`](oci_client::client::ClientConfig) that selects the wasm
+/// entry of an image index whose OS comes first in the given list
+pub fn wasm_platform_resolver_with_preference(preferred_os: Vec<WasmOs>) -> PlatformResolver {
+    Box::new(move |manifests| {
+        select_platform(manifests, &preferred_os).map(|entry| entry.digest.clone())
+    })
+}
+
+/// Selects the first entry in the index that has the wasm architecture and the most preferred OS
+pub(crate) fn select_platform<'a>(
+    manifests: &'a [ImageIndexEntry],
+    preferred_os: &[WasmOs],
+) -> Option<&'a ImageIndexEntry> {
+    let architecture = Arch::from(WASM_ARCHITECTURE);
+    preferred_os.iter().find_map(|os| {
+        let os = Os::from(os.as_str());
+        manifests.iter().find(|entry| {
+            entry
+                .platform
+                .as_ref()
+                .is_some_and(|platform| platform.architecture == architecture && platform.os == os)
+        })
+    })
+}
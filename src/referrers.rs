@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+
+use oci_client::{
+    errors::{OciDistributionError, OciErrorCode},
+    manifest::{OciImageManifest, OCI_IMAGE_INDEX_MEDIA_TYPE},
+};
+use serde::{Deserialize, Serialize};
+
+/// The media type of the empty descriptor used as the config of artifacts that have no config
+pub(crate) const EMPTY_MEDIA_TYPE: &str = "application/vnd.oci.empty.v1+json";
+/// The contents of the empty descriptor blob
+pub(crate) const EMPTY_DATA: &[u8] = b"{}";
+
+/// An artifact that refers to a wasm manifest through its `subject` field, as returned by
+/// [`WasmClient::referrers`](crate::WasmClient::referrers)
+#[derive(Debug, Clone)]
+pub struct Referrer {
+    /// The digest of the referrer's manifest
+    pub digest: String,
+    /// The artifact type of the referrer. This falls back to the config media type for
+    /// manifests that don't set `artifactType`, as required by the OCI image spec
+    pub artifact_type: String,
+    /// The referrer's manifest, which lists the blobs attached to the subject
+    pub manifest: OciImageManifest,
+}
+
+/// An image index as stored under the referrers tag schema. This is used instead of
+/// [`OciImageIndex`](oci_client::manifest::OciImageIndex) because its entries don't keep the
+/// `artifactType` field that clients use to filter referrers
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReferrersIndex {
+    pub schema_version: u8,
+    pub media_type: String,
+    pub manifests: Vec<ReferrerDescriptor>,
+}
+
+impl Default for ReferrersIndex {
+    fn default() -> Self {
+        Self {
+            schema_version: 2,
+            media_type: OCI_IMAGE_INDEX_MEDIA_TYPE.to_string(),
+            manifests: Vec::new(),
+        }
+    }
+}
+
+/// A descriptor in a [`ReferrersIndex`]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReferrerDescriptor {
+    pub media_type: String,
+    pub digest: String,
+    pub size: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artifact_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<BTreeMap<String, String>>,
+}
+
+/// Returns the tag that referrers of the manifest with the given digest are listed under on
+/// registries without the referrers API, e.g. `sha256-<hex encoded digest>`
+pub(crate) fn fallback_tag(digest: &str) -> String {
+    digest.replacen(':', "-", 1)
+}
+
+/// Returns true if the error means the registry doesn't support the referrers API, or that the
+/// requested manifest doesn't exist, so the tag schema should be used instead
+pub(crate) fn is_not_found(error: &OciDistributionError) -> bool {
+    match error {
+        OciDistributionError::ServerError { code, .. } => *code == 404,
+        OciDistributionError::ImageManifestNotFoundError(_) => true,
+        OciDistributionError::RegistryError { envelope, .. } => envelope.errors.iter().any(|e| {
+            matches!(
+                e.code,
+                OciErrorCode::NotFound
+                    | OciErrorCode::ManifestUnknown
+                    | OciErrorCode::NameUnknown
+                    | OciErrorCode::Unsupported
+            )
+        }),
+        _ => false,
+    }
+}
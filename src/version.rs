@@ -0,0 +1,29 @@
+use semver::{Prerelease, Version, VersionReq};
+
+/// Selects the highest tag matching the given version requirement. Tags that aren't valid semver
+/// versions (optionally prefixed with `v`) are ignored.
+///
+/// Pre-release versions only match requirements that name a pre-release of the same version
+/// (e.g. `1.3.0-rc.1` matches `^1.3.0-rc.1`), following semver's own rules. If
+/// `include_prerelease` is set, they also match as if they were the release they precede (e.g.
+/// `1.3.0-rc.1` matches `^1.2`)
+pub fn resolve_tag<'a>(
+    tags: impl IntoIterator<Item = &'a str>,
+    req: &VersionReq,
+    include_prerelease: bool,
+) -> Option<&'a str> {
+    tags.into_iter()
+        .filter_map(|tag| {
+            let version = Version::parse(tag.strip_prefix('v').unwrap_or(tag)).ok()?;
+            let matches = req.matches(&version)
+                || (include_prerelease
+                    && !version.pre.is_empty()
+                    && req.matches(&Version {
+                        pre: Prerelease::EMPTY,
+                        ..version.clone()
+                    }));
+            matches.then_some((version, tag))
+        })
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, tag)| tag)
+}
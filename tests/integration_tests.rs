@@ -1,16 +1,25 @@
 use anyhow::Context;
 use oci_client::{
-    client::{ClientConfig, ClientProtocol},
+    client::{ClientConfig, ClientProtocol, ImageData, ImageLayer},
     errors::OciDistributionError,
 };
 use oci_spec::image::{Arch, Os};
 use oci_wasm::{
-    Component, WasmClient, WasmConfig, COMPONENT_OS, WASM_ARCHITECTURE, WASM_LAYER_MEDIA_TYPE,
-    WASM_MANIFEST_CONFIG_MEDIA_TYPE, WASM_MANIFEST_MEDIA_TYPE,
+    read_image_archive, read_image_layout, resolve_tag, wasm_platform_resolver,
+    wasm_platform_resolver_with_preference, write_image_archive, write_image_layout, Cache,
+    CacheConfig, Component, ConfigViolation, Error, FunctionMetadata, HostCapabilities,
+    ModuleExport, ModuleImport, ModuleItemKind, SigningKey, ToConfig, TypeMetadata, WasiSnapshot,
+    WasmClient, WasmConfig, WasmKind, WasmOs, COMPONENT_OS, WASM_ARCHITECTURE,
+    WASM_LAYER_MEDIA_TYPE, WASM_MANIFEST_CONFIG_MEDIA_TYPE, WASM_MANIFEST_MEDIA_TYPE,
 };
-use testcontainers::{core::WaitFor, runners::AsyncRunner, ContainerAsync, Image};
+use testcontainers::{core::WaitFor, runners::AsyncRunner, ContainerAsync, Image, ImageExt};
 
 const DOCKER_REGISTRY_PORT: u16 = 5000;
+/// Bcrypt hashes for the users `staging` (password `staging-password`) and `prod` (password
+/// `prod-password`)
+const HTPASSWD: &str = "staging:$2b$05$OA0GKSbXWFmTzaI0VJZal.Pp8aNEkS8IFn5NxKwTqsuLpmoI/E51i
+prod:$2b$05$xVG4pHVg.lJuT7LRloaWierOeLgnXMCqxuroOajpONXmip9hyDV6y
+";
 
 #[derive(Default)]
 struct DockerRegistry {
@@ -38,6 +47,17 @@ async fn setup_registry() -> anyhow::Result<ContainerAsync<DockerRegistry>> {
         .context("Failed to start docker registry")
 }
 
+async fn setup_registry_with_auth() -> anyhow::Result<ContainerAsync<DockerRegistry>> {
+    DockerRegistry::default()
+        .with_env_var("REGISTRY_AUTH", "htpasswd")
+        .with_env_var("REGISTRY_AUTH_HTPASSWD_REALM", "test")
+        .with_env_var("REGISTRY_AUTH_HTPASSWD_PATH", "/auth/htpasswd")
+        .with_copy_to("/auth/htpasswd", HTPASSWD.as_bytes().to_vec())
+        .start()
+        .await
+        .context("Failed to start docker registry")
+}
+
 fn setup_client(registry_address: String) -> WasmClient {
     let client = oci_client::Client::new(ClientConfig {
         protocol: ClientProtocol::HttpsExcept(vec![registry_address]),
@@ -178,10 +198,20 @@ async fn test_push_and_pull() {
     // As a sanity check, make sure we can still parse the bytes out (by loading a component)
     let _ = Component::from_raw_component(&data.layers[0].data)
         .expect("Returned bytes should still be valid");
+
+    let (data, conf) = client
+        .pull_and_verify(&image, &oci_client::secrets::RegistryAuth::Anonymous)
+        .await
+        .expect("Should be able to pull and verify component");
+    assert_eq!(
+        conf.layer_digests,
+        vec![data.layers[0].sha256_digest()],
+        "Should have the layer digest recorded in the config"
+    );
 }
 
 #[tokio::test]
-async fn pulling_non_wasm_should_fail() {
+async fn test_streaming_push_and_pull() {
     let registry = setup_registry()
         .await
         .expect("Should be able to start docker registry");
@@ -193,58 +223,2180 @@ async fn pulling_non_wasm_should_fail() {
         .get_host_port_ipv4(DOCKER_REGISTRY_PORT)
         .await
         .expect("Should be able to get port for docker registry");
+    let registry_address = format!("{registry_ip}:{registry_port}");
 
-    let client = setup_client(format!("{registry_ip}:{registry_port}"));
-    // Using an older wasmcloud image because otherwise the pull doesn't work due to platform
-    // mismatch on things like a Mac. I tried this with an alpine image first ghcr.io/wasmcloud/component-echo-messaging:0.1.0
-    let image = oci_client::Reference::try_from("docker.io/library/alpine:3").unwrap();
-    // ImageData doesn't implement debug so we can't use `expect_err` here
+    let client = setup_client(registry_address.clone());
+
+    let image = oci_client::Reference::try_from(format!("{registry_address}/test/streaming:0.0.1"))
+        .unwrap();
+
+    let (conf, layer) = WasmConfig::from_component("./tests/data/component.wasm", None)
+        .await
+        .expect("Should be able to parse component and create config");
+    client
+        .push_from_file(
+            &image,
+            &oci_client::secrets::RegistryAuth::Anonymous,
+            "./tests/data/component.wasm",
+            conf,
+            None,
+        )
+        .await
+        .expect("Should be able to push component from file");
+
+    let path = std::env::temp_dir().join(format!("oci-wasm-stream-{}.wasm", std::process::id()));
+    let (manifest, conf, _) = client
+        .pull_to_file(&image, &oci_client::secrets::RegistryAuth::Anonymous, &path)
+        .await
+        .expect("Should be able to pull component to file");
+    let pulled = tokio::fs::read(&path)
+        .await
+        .expect("Should be able to read pulled file");
+    let _ = tokio::fs::remove_file(&path).await;
+
+    assert_eq!(
+        pulled,
+        layer.data.to_vec(),
+        "Pulled bytes should match the pushed file"
+    );
+    assert_eq!(
+        manifest.layers[0].digest,
+        layer.sha256_digest(),
+        "Should have the correct layer digest in the manifest"
+    );
+    assert_eq!(
+        conf.layer_digests,
+        vec![layer.sha256_digest()],
+        "Should have the correct layer digest in the config"
+    );
+
+    // Pushing with a config that doesn't match the file should fail before anything is pushed
+    let (conf, _) = WasmConfig::from_raw_module(wasi_module(), None)
+        .expect("Should be able to create module config");
+    // PushResponse doesn't implement debug so we can't use `expect_err` here
     let err = match client
-        .pull(&image, &oci_client::secrets::RegistryAuth::Anonymous)
+        .push_from_file(
+            &image,
+            &oci_client::secrets::RegistryAuth::Anonymous,
+            "./tests/data/component.wasm",
+            conf,
+            None,
+        )
         .await
     {
-        Ok(_) => panic!("Should not be able to pull non wasm component"),
+        Ok(_) => panic!("Should not be able to push with mismatched config"),
         Err(e) => e,
     };
     assert!(
-        matches!(
-            err.downcast::<OciDistributionError>().unwrap(),
-            OciDistributionError::IncompatibleLayerMediaTypeError(_)
-        ),
-        "Should have returned an incompatible layer media type error"
+        matches!(err, Error::LayerDigestMismatch { .. }),
+        "Should have returned a layer digest mismatch error"
     );
 }
 
 #[tokio::test]
-async fn test_binary_wit_parse() {
-    let (conf, _) = WasmConfig::from_component("./tests/data/binary_wit.wasm", None)
+async fn test_copy() {
+    let registry = setup_registry()
         .await
-        .expect("Should be able to parse binary wit");
+        .expect("Should be able to start docker registry");
+    let registry_ip = registry
+        .get_host()
+        .await
+        .expect("Should be able to get ip for docker registry");
+    let registry_port = registry
+        .get_host_port_ipv4(DOCKER_REGISTRY_PORT)
+        .await
+        .expect("Should be able to get port for docker registry");
+    let registry_address = format!("{registry_ip}:{registry_port}");
+
+    let client = setup_client(registry_address.clone());
+    let auth = oci_client::secrets::RegistryAuth::Anonymous;
 
+    let src =
+        oci_client::Reference::try_from(format!("{registry_address}/staging/test:0.0.1")).unwrap();
+    let dst =
+        oci_client::Reference::try_from(format!("{registry_address}/prod/test:0.0.1")).unwrap();
+
+    let (conf, component) = WasmConfig::from_component("./tests/data/component.wasm", None)
+        .await
+        .expect("Should be able to parse component and create config");
+    let annotations = std::collections::BTreeMap::from([(
+        "org.opencontainers.image.version".to_string(),
+        "0.0.1".to_string(),
+    )]);
+    client
+        .push(&src, &auth, component, conf, Some(annotations.clone()))
+        .await
+        .expect("Should be able to push component");
+
+    let digest = client
+        .copy(&src, &dst, &auth, &auth)
+        .await
+        .expect("Should be able to copy component");
+
+    let (src_manifest, _, src_digest) = client
+        .pull_manifest_and_config(&src, &auth)
+        .await
+        .expect("Should be able to pull source manifest");
+    let (dst_manifest, _, dst_digest) = client
+        .pull_manifest_and_config(&dst, &auth)
+        .await
+        .expect("Should be able to pull copied manifest");
+    assert_eq!(digest, src_digest, "Should return the source digest");
     assert_eq!(
-        conf.architecture, WASM_ARCHITECTURE,
-        "Should have the correct architecture set in config"
+        src_digest, dst_digest,
+        "Copied manifest should have an identical digest"
     );
-    assert_eq!(conf.os, COMPONENT_OS, "Should have the right OS value set");
-    let component_info = conf
-        .component
-        .expect("Should have component information set in config");
+    assert_eq!(
+        dst_manifest.annotations,
+        Some(annotations),
+        "Should preserve the manifest annotations"
+    );
+    assert_eq!(
+        src_manifest.layers[0].digest, dst_manifest.layers[0].digest,
+        "Should have identical layer digests"
+    );
+    client
+        .pull_and_verify(&dst, &auth)
+        .await
+        .expect("Should be able to pull the copied component");
+}
 
-    let mut expected_exports = vec![
-        "wasi:http/incoming-handler@0.2.0".to_string(),
-        "wasi:http/types@0.2.0".to_string(),
-        "wasi:http/outgoing-handler@0.2.0".to_string(),
-        "wasi:http/proxy@0.2.0".to_string(),
-        "wasi:http/imports@0.2.0".to_string(),
-    ];
-    expected_exports.sort();
+#[tokio::test]
+async fn test_copy_with_different_credentials() {
+    let registry = setup_registry_with_auth()
+        .await
+        .expect("Should be able to start docker registry");
+    let registry_ip = registry
+        .get_host()
+        .await
+        .expect("Should be able to get ip for docker registry");
+    let registry_port = registry
+        .get_host_port_ipv4(DOCKER_REGISTRY_PORT)
+        .await
+        .expect("Should be able to get port for docker registry");
+    let registry_address = format!("{registry_ip}:{registry_port}");
 
-    let mut exports = component_info.exports;
-    exports.sort();
+    let client = setup_client(registry_address.clone());
+    let src_auth = oci_client::secrets::RegistryAuth::Basic(
+        "staging".to_string(),
+        "staging-password".to_string(),
+    );
+    let dst_auth =
+        oci_client::secrets::RegistryAuth::Basic("prod".to_string(), "prod-password".to_string());
+    let bad_auth =
+        oci_client::secrets::RegistryAuth::Basic("prod".to_string(), "wrong".to_string());
+
+    let src =
+        oci_client::Reference::try_from(format!("{registry_address}/staging/test:0.0.1")).unwrap();
+    let dst =
+        oci_client::Reference::try_from(format!("{registry_address}/prod/test:0.0.1")).unwrap();
+
+    let (conf, component) = WasmConfig::from_component("./tests/data/component.wasm", None)
+        .await
+        .expect("Should be able to parse component and create config");
+    client
+        .push(&src, &src_auth, component, conf, None)
+        .await
+        .expect("Should be able to push component");
+
+    client
+        .copy(&src, &dst, &src_auth, &bad_auth)
+        .await
+        .expect_err("Should not push to the destination with the source credentials");
 
+    let digest = client
+        .copy(&src, &dst, &src_auth, &dst_auth)
+        .await
+        .expect("Should be able to copy component");
+    let (_, _, dst_digest) = setup_client(registry_address)
+        .pull_manifest_and_config(&dst, &dst_auth)
+        .await
+        .expect("Should be able to pull copied manifest");
     assert_eq!(
-        exports, expected_exports,
-        "Expected exports to match:\nGot: {exports:?}\nExpected:\n{expected_exports:?}"
+        digest, dst_digest,
+        "Copied manifest should have the source digest"
     );
-    assert!(component_info.imports.is_empty(), "Should have no imports");
+}
+
+#[tokio::test]
+async fn test_push_index() {
+    let registry = setup_registry()
+        .await
+        .expect("Should be able to start docker registry");
+    let registry_ip = registry
+        .get_host()
+        .await
+        .expect("Should be able to get ip for docker registry");
+    let registry_port = registry
+        .get_host_port_ipv4(DOCKER_REGISTRY_PORT)
+        .await
+        .expect("Should be able to get port for docker registry");
+    let registry_address = format!("{registry_ip}:{registry_port}");
+
+    let client = setup_client(registry_address.clone());
+    let auth = oci_client::secrets::RegistryAuth::Anonymous;
+    let image =
+        oci_client::Reference::try_from(format!("{registry_address}/multi/app:0.0.1")).unwrap();
+
+    let (component_conf, component) =
+        WasmConfig::from_component("./tests/data/component.wasm", None)
+            .await
+            .expect("Should be able to parse component and create config");
+    let (module_conf, module) = WasmConfig::from_raw_module(wasi_module(), None)
+        .expect("Should be able to create module config");
+    let component_digest = component.sha256_digest();
+    let module_digest = module.sha256_digest();
+    client
+        .push_index(
+            &image,
+            &auth,
+            vec![(module, module_conf), (component, component_conf)],
+            None,
+        )
+        .await
+        .expect("Should be able to push index");
+
+    let (data, conf) = client
+        .pull_platform(&image, &auth, &[WasmOs::Wasip2, WasmOs::Wasip1])
+        .await
+        .expect("Should be able to pull component from index");
+    assert_eq!(conf.os, WasmOs::Wasip2, "Should prefer the component");
+    assert_eq!(
+        data.layers[0].sha256_digest(),
+        component_digest,
+        "Should pull the component layer"
+    );
+
+    let (data, conf) = client
+        .pull_platform(&image, &auth, &[WasmOs::Wasip1])
+        .await
+        .expect("Should be able to pull module from index");
+    assert_eq!(conf.os, WasmOs::Wasip1, "Should select the module");
+    assert_eq!(
+        data.layers[0].sha256_digest(),
+        module_digest,
+        "Should pull the module layer"
+    );
+
+    let err = match client.pull_platform(&image, &auth, &[WasmOs::Wasip3]).await {
+        Ok(_) => panic!("Should not find a wasip3 artifact"),
+        Err(e) => e,
+    };
+    assert!(
+        matches!(err, Error::NoMatchingPlatform(ref os) if os == &[WasmOs::Wasip3]),
+        "Should return a no matching platform error, got {err:?}"
+    );
+}
+
+#[tokio::test]
+async fn test_referrers() {
+    let registry = setup_registry()
+        .await
+        .expect("Should be able to start docker registry");
+    let registry_ip = registry
+        .get_host()
+        .await
+        .expect("Should be able to get ip for docker registry");
+    let registry_port = registry
+        .get_host_port_ipv4(DOCKER_REGISTRY_PORT)
+        .await
+        .expect("Should be able to get port for docker registry");
+    let registry_address = format!("{registry_ip}:{registry_port}");
+
+    let client = setup_client(registry_address.clone());
+    let auth = oci_client::secrets::RegistryAuth::Anonymous;
+    let image =
+        oci_client::Reference::try_from(format!("{registry_address}/attach/app:0.0.1")).unwrap();
+
+    let (conf, component) = WasmConfig::from_component("./tests/data/component.wasm", None)
+        .await
+        .expect("Should be able to parse component and create config");
+    client
+        .push(&image, &auth, component, conf, None)
+        .await
+        .expect("Should be able to push component");
+
+    let docs = ImageLayer::new(b"# Docs".to_vec(), "text/markdown".to_string(), None);
+    let docs_digest = client
+        .attach(
+            &image,
+            &auth,
+            "application/vnd.example.docs",
+            vec![docs.clone()],
+            None,
+        )
+        .await
+        .expect("Should be able to attach docs");
+    let sbom_digest = client
+        .attach(
+            &image,
+            &auth,
+            "application/vnd.example.sbom",
+            Vec::new(),
+            Some([("org.example.tool".to_string(), "test".to_string())].into()),
+        )
+        .await
+        .expect("Should be able to attach an sbom without blobs");
+
+    let referrers = client
+        .referrers(&image, &auth, None)
+        .await
+        .expect("Should be able to list referrers");
+    assert_eq!(referrers.len(), 2, "Should find both referrers");
+
+    let referrers = client
+        .referrers(&image, &auth, Some("application/vnd.example.docs"))
+        .await
+        .expect("Should be able to list filtered referrers");
+    assert_eq!(referrers.len(), 1, "Should only find the docs referrer");
+    assert_eq!(referrers[0].digest, docs_digest);
+    assert_eq!(referrers[0].artifact_type, "application/vnd.example.docs");
+    assert_eq!(
+        referrers[0].manifest.layers[0].digest,
+        docs.sha256_digest(),
+        "Should list the attached blob"
+    );
+
+    let referrers = client
+        .referrers(&image, &auth, Some("application/vnd.example.sbom"))
+        .await
+        .expect("Should be able to list filtered referrers");
+    assert_eq!(referrers.len(), 1, "Should only find the sbom referrer");
+    assert_eq!(referrers[0].digest, sbom_digest);
+
+    let missing =
+        oci_client::Reference::try_from(format!("{registry_address}/attach/app:missing")).unwrap();
+    client
+        .attach(
+            &missing,
+            &auth,
+            "application/vnd.example.docs",
+            Vec::new(),
+            None,
+        )
+        .await
+        .expect_err("Should not be able to attach to a missing subject");
+}
+
+#[tokio::test]
+async fn test_sign_and_pull_verified() {
+    let registry = setup_registry()
+        .await
+        .expect("Should be able to start docker registry");
+    let registry_ip = registry
+        .get_host()
+        .await
+        .expect("Should be able to get ip for docker registry");
+    let registry_port = registry
+        .get_host_port_ipv4(DOCKER_REGISTRY_PORT)
+        .await
+        .expect("Should be able to get port for docker registry");
+    let registry_address = format!("{registry_ip}:{registry_port}");
+
+    let client = setup_client(registry_address.clone());
+    let auth = oci_client::secrets::RegistryAuth::Anonymous;
+    let image =
+        oci_client::Reference::try_from(format!("{registry_address}/signed/app:0.0.1")).unwrap();
+
+    let (conf, component) = WasmConfig::from_component("./tests/data/component.wasm", None)
+        .await
+        .expect("Should be able to parse component and create config");
+    let expected_digest = component.sha256_digest();
+    client
+        .push(&image, &auth, component, conf, None)
+        .await
+        .expect("Should be able to push component");
+
+    let ecdsa = SigningKey::generate_ecdsa_p256();
+    let ed25519 = SigningKey::generate_ed25519();
+
+    let err = match client
+        .pull_verified(&image, &auth, &[ecdsa.verifying_key()])
+        .await
+    {
+        Ok(_) => panic!("Should not pull an unsigned component"),
+        Err(e) => e,
+    };
+    assert!(
+        matches!(err, Error::NoValidSignature(_)),
+        "Should return a no valid signature error, got {err:?}"
+    );
+
+    client
+        .sign(&image, &auth, &ecdsa)
+        .await
+        .expect("Should be able to sign with an ECDSA key");
+    let err = match client
+        .pull_verified(&image, &auth, &[ed25519.verifying_key()])
+        .await
+    {
+        Ok(_) => panic!("Should not accept a signature from an unknown key"),
+        Err(e) => e,
+    };
+    assert!(
+        matches!(err, Error::NoValidSignature(_)),
+        "Should return a no valid signature error, got {err:?}"
+    );
+
+    let (data, _) = client
+        .pull_verified(&image, &auth, &[ecdsa.verifying_key()])
+        .await
+        .expect("Should be able to pull a component signed with a trusted key");
+    assert_eq!(data.layers[0].sha256_digest(), expected_digest);
+
+    client
+        .sign(&image, &auth, &ed25519)
+        .await
+        .expect("Should be able to add an ed25519 signature");
+    client
+        .pull_verified(&image, &auth, &[ed25519.verifying_key()])
+        .await
+        .expect("Should be able to verify the second signature");
+    client
+        .pull_verified(&image, &auth, &[ecdsa.verifying_key()])
+        .await
+        .expect("Should keep the first signature");
+}
+
+#[tokio::test]
+async fn test_pull_embedded_signature() {
+    let registry = setup_registry()
+        .await
+        .expect("Should be able to start docker registry");
+    let registry_ip = registry
+        .get_host()
+        .await
+        .expect("Should be able to get ip for docker registry");
+    let registry_port = registry
+        .get_host_port_ipv4(DOCKER_REGISTRY_PORT)
+        .await
+        .expect("Should be able to get port for docker registry");
+    let registry_address = format!("{registry_ip}:{registry_port}");
+
+    let client = setup_client(registry_address.clone());
+    let auth = oci_client::secrets::RegistryAuth::Anonymous;
+    let signed_image =
+        oci_client::Reference::try_from(format!("{registry_address}/embedded/app:signed")).unwrap();
+    let unsigned_image =
+        oci_client::Reference::try_from(format!("{registry_address}/embedded/app:unsigned"))
+            .unwrap();
+
+    let key = SigningKey::generate_ed25519();
+    let (conf, component) =
+        WasmConfig::from_component_signed("./tests/data/component.wasm", None, &key)
+            .await
+            .expect("Should be able to sign component");
+    client
+        .push(&signed_image, &auth, component, conf, None)
+        .await
+        .expect("Should be able to push signed component");
+    let (conf, component) = WasmConfig::from_component("./tests/data/component.wasm", None)
+        .await
+        .expect("Should be able to parse component and create config");
+    client
+        .push(&unsigned_image, &auth, component, conf, None)
+        .await
+        .expect("Should be able to push unsigned component");
+    // Signed by a trusted key, but the config names another one as the signer
+    let other = SigningKey::generate_ed25519();
+    let mismatched_image =
+        oci_client::Reference::try_from(format!("{registry_address}/embedded/app:mismatched"))
+            .unwrap();
+    let (mut conf, component) =
+        WasmConfig::from_component_signed("./tests/data/component.wasm", None, &key)
+            .await
+            .expect("Should be able to sign component");
+    conf.signer_key_id = Some(other.verifying_key().key_id());
+    client
+        .push(&mismatched_image, &auth, component, conf, None)
+        .await
+        .expect("Should be able to push component with a mismatched signer");
+
+    assert!(
+        matches!(
+            setup_client(registry_address.clone())
+                .with_trusted_keys([SigningKey::generate_ecdsa_p256().verifying_key()]),
+            Err(Error::InvalidKey(_))
+        ),
+        "Should reject keys that can't make embedded signatures"
+    );
+    let client = client
+        .with_trusted_keys([key.verifying_key(), other.verifying_key()])
+        .expect("Should accept ed25519 keys");
+    let (_, conf) = client
+        .pull_and_verify(&signed_image, &auth)
+        .await
+        .expect("Should be able to pull signed component");
+    assert_eq!(conf.signer_key_id, Some(key.verifying_key().key_id()));
+
+    let err = match client.pull(&unsigned_image, &auth).await {
+        Ok(_) => panic!("Should not pull an unsigned component"),
+        Err(e) => e,
+    };
+    assert!(
+        matches!(err, Error::NoValidSignature(_)),
+        "Should return a no valid signature error, got {err:?}"
+    );
+
+    let path = std::env::temp_dir().join(format!("oci-wasm-signed-{}.wasm", std::process::id()));
+    client
+        .pull_to_file(&signed_image, &auth, &path)
+        .await
+        .expect("Should be able to stream signed component to a file");
+    let data = tokio::fs::read(&path)
+        .await
+        .expect("Should be able to read pulled file");
+    assert!(
+        key.verifying_key().verify_embedded(&data),
+        "Pulled file should carry the embedded signature"
+    );
+    let err = client
+        .pull_to_file(&unsigned_image, &auth, &path)
+        .await
+        .expect_err("Should not stream an unsigned component to a file");
+    assert!(
+        matches!(err, Error::NoValidSignature(_)),
+        "Should return a no valid signature error, got {err:?}"
+    );
+    assert_eq!(
+        tokio::fs::read(&path)
+            .await
+            .expect("Should keep the previously pulled file"),
+        data,
+        "Should leave the previously pulled file unchanged when the signature check fails"
+    );
+    let _ = tokio::fs::remove_file(&path).await;
+
+    let mut buf = Vec::new();
+    let err = client
+        .pull_to_writer(&unsigned_image, &auth, &mut buf)
+        .await
+        .expect_err("Should not stream an unsigned component to a writer");
+    assert!(
+        matches!(err, Error::NoValidSignature(_)),
+        "Should return a no valid signature error, got {err:?}"
+    );
+    assert!(buf.is_empty(), "Should not write unverified bytes");
+
+    let err = match client.pull(&mismatched_image, &auth).await {
+        Ok(_) => panic!("Should not pull a component signed by a key other than the signer"),
+        Err(e) => e,
+    };
+    assert!(
+        matches!(err, Error::SignerMismatch { ref expected, ref actual }
+            if *expected == other.verifying_key().key_id()
+                && *actual == key.verifying_key().key_id()),
+        "Should return a signer mismatch error, got {err:?}"
+    );
+    let err = client
+        .pull_to_writer(&mismatched_image, &auth, &mut buf)
+        .await
+        .expect_err("Should not stream a component signed by a key other than the signer");
+    assert!(
+        matches!(err, Error::SignerMismatch { .. }),
+        "Should return a signer mismatch error, got {err:?}"
+    );
+
+    let client = client
+        .with_trusted_keys([SigningKey::generate_ed25519().verifying_key()])
+        .expect("Should accept an ed25519 key");
+    let err = match client.pull(&signed_image, &auth).await {
+        Ok(_) => panic!("Should not pull a component signed by an untrusted key"),
+        Err(e) => e,
+    };
+    assert!(
+        matches!(err, Error::NoValidSignature(_)),
+        "Should return a no valid signature error, got {err:?}"
+    );
+}
+
+#[tokio::test]
+async fn test_resolve_version() {
+    let registry = setup_registry()
+        .await
+        .expect("Should be able to start docker registry");
+    let registry_ip = registry
+        .get_host()
+        .await
+        .expect("Should be able to get ip for docker registry");
+    let registry_port = registry
+        .get_host_port_ipv4(DOCKER_REGISTRY_PORT)
+        .await
+        .expect("Should be able to get port for docker registry");
+    let registry_address = format!("{registry_ip}:{registry_port}");
+
+    let client = setup_client(registry_address.clone());
+    let auth = oci_client::secrets::RegistryAuth::Anonymous;
+
+    let (conf, component) = WasmConfig::from_component("./tests/data/component.wasm", None)
+        .await
+        .expect("Should be able to parse component and create config");
+    for tag in ["0.1.0", "0.2.0", "0.2.3", "0.3.0-rc.1", "latest"] {
+        let image =
+            oci_client::Reference::try_from(format!("{registry_address}/test/versions:{tag}"))
+                .unwrap();
+        client
+            .push(&image, &auth, component.clone(), &conf, None)
+            .await
+            .expect("Should be able to push component");
+    }
+
+    let image =
+        oci_client::Reference::try_from(format!("{registry_address}/test/versions")).unwrap();
+    let resolved = client
+        .resolve_version(
+            &image,
+            &auth,
+            &semver::VersionReq::parse("^0.2").unwrap(),
+            false,
+        )
+        .await
+        .expect("Should be able to resolve version");
+    assert_eq!(
+        resolved.tag(),
+        Some("0.2.3"),
+        "Should resolve the highest match"
+    );
+    client
+        .pull(&resolved, &auth)
+        .await
+        .expect("Should be able to pull resolved reference");
+
+    let err = client
+        .resolve_version(
+            &image,
+            &auth,
+            &semver::VersionReq::parse("^1").unwrap(),
+            false,
+        )
+        .await
+        .expect_err("Should not resolve a version that doesn't exist");
+    assert!(
+        matches!(err, Error::NoMatchingVersion(_)),
+        "Should have returned a no matching version error"
+    );
+}
+
+#[tokio::test]
+async fn test_cached_pull() {
+    let registry = setup_registry()
+        .await
+        .expect("Should be able to start docker registry");
+    let registry_ip = registry
+        .get_host()
+        .await
+        .expect("Should be able to get ip for docker registry");
+    let registry_port = registry
+        .get_host_port_ipv4(DOCKER_REGISTRY_PORT)
+        .await
+        .expect("Should be able to get port for docker registry");
+    let registry_address = format!("{registry_ip}:{registry_port}");
+
+    let cache_dir = std::env::temp_dir().join(format!("oci-wasm-cache-{}", std::process::id()));
+    let cache = Cache::new(CacheConfig {
+        tag_ttl: std::time::Duration::from_secs(3600),
+        ..CacheConfig::new(&cache_dir)
+    });
+    cache.clear().await.expect("Should be able to clear cache");
+    let client = setup_client(registry_address.clone()).with_cache(cache.clone());
+    let auth = oci_client::secrets::RegistryAuth::Anonymous;
+
+    let image =
+        oci_client::Reference::try_from(format!("{registry_address}/test/cached:0.0.1")).unwrap();
+    let (conf, component) = WasmConfig::from_component("./tests/data/component.wasm", None)
+        .await
+        .expect("Should be able to parse component and create config");
+    client
+        .push(&image, &auth, component.clone(), conf, None)
+        .await
+        .expect("Should be able to push component");
+
+    // Concurrent pulls of the same image write the same cache entries at the same time
+    let (data, other) =
+        futures_util::future::join(client.pull(&image, &auth), client.pull(&image, &auth)).await;
+    let data = data.expect("Should be able to pull component");
+    other.expect("Should be able to pull component concurrently");
+    assert!(
+        cache_dir
+            .join("blobs/sha256")
+            .join(component.sha256_digest().trim_start_matches("sha256:"))
+            .exists(),
+        "Layer should be in the cache"
+    );
+
+    // A tiny size limit should evict everything as soon as it is stored
+    let small_dir = cache_dir.with_extension("small");
+    let small = Cache::new(CacheConfig {
+        max_size: 1,
+        ..CacheConfig::new(&small_dir)
+    });
+    setup_client(registry_address.clone())
+        .with_cache(small.clone())
+        .pull(&image, &auth)
+        .await
+        .expect("Should be able to pull component with a small cache");
+    assert!(
+        !small_dir
+            .join("blobs/sha256")
+            .join(component.sha256_digest().trim_start_matches("sha256:"))
+            .exists(),
+        "Layer should have been evicted from the small cache"
+    );
+    small.clear().await.expect("Should be able to clear cache");
+
+    // A tampered manifest should be treated as a miss and pulled again
+    let manifest_path = cache_dir.join("manifests/sha256").join(
+        data.digest
+            .as_deref()
+            .expect("Should have a manifest digest")
+            .trim_start_matches("sha256:"),
+    );
+    tokio::fs::write(&manifest_path, b"{}")
+        .await
+        .expect("Should be able to tamper with the cached manifest");
+    client
+        .pull(&image, &auth)
+        .await
+        .expect("Should be able to pull component with a tampered manifest");
+    assert_ne!(
+        tokio::fs::read(&manifest_path)
+            .await
+            .expect("Manifest should be in the cache"),
+        b"{}",
+        "Should replace the tampered manifest"
+    );
+
+    // With the registry gone, everything should come from the cache
+    registry
+        .stop()
+        .await
+        .expect("Should be able to stop docker registry");
+    let cached = client
+        .pull(&image, &auth)
+        .await
+        .expect("Should be able to pull component from cache");
+    assert_eq!(cached.digest, data.digest, "Should have the same digest");
+    assert_eq!(
+        cached.layers[0].data, component.data,
+        "Should have the same layer data"
+    );
+    let (_, conf, _) = client
+        .pull_manifest_and_config(&image, &auth)
+        .await
+        .expect("Should be able to pull manifest and config from cache");
+    assert_eq!(conf.os, COMPONENT_OS, "Should have the right OS value set");
+
+    cache.clear().await.expect("Should be able to clear cache");
+}
+
+#[tokio::test]
+async fn pulling_non_wasm_should_fail() {
+    let registry = setup_registry()
+        .await
+        .expect("Should be able to start docker registry");
+    let registry_ip = registry
+        .get_host()
+        .await
+        .expect("Should be able to get ip for docker registry");
+    let registry_port = registry
+        .get_host_port_ipv4(DOCKER_REGISTRY_PORT)
+        .await
+        .expect("Should be able to get port for docker registry");
+
+    let client = setup_client(format!("{registry_ip}:{registry_port}"));
+    // Using an older wasmcloud image because otherwise the pull doesn't work due to platform
+    // mismatch on things like a Mac. I tried this with an alpine image first ghcr.io/wasmcloud/component-echo-messaging:0.1.0
+    let image = oci_client::Reference::try_from("docker.io/library/alpine:3").unwrap();
+    // ImageData doesn't implement debug so we can't use `expect_err` here
+    let err = match client
+        .pull(&image, &oci_client::secrets::RegistryAuth::Anonymous)
+        .await
+    {
+        Ok(_) => panic!("Should not be able to pull non wasm component"),
+        Err(e) => e,
+    };
+    assert!(
+        matches!(
+            err,
+            Error::Oci(OciDistributionError::IncompatibleLayerMediaTypeError(_))
+        ),
+        "Should have returned an incompatible layer media type error"
+    );
+}
+
+#[tokio::test]
+async fn test_binary_wit_parse() {
+    let (conf, _) = WasmConfig::from_component("./tests/data/binary_wit.wasm", None)
+        .await
+        .expect("Should be able to parse binary wit");
+
+    assert_eq!(
+        conf.architecture, WASM_ARCHITECTURE,
+        "Should have the correct architecture set in config"
+    );
+    assert_eq!(conf.os, COMPONENT_OS, "Should have the right OS value set");
+    let component_info = conf
+        .component
+        .expect("Should have component information set in config");
+
+    let mut expected_exports = vec![
+        "wasi:http/incoming-handler@0.2.0".to_string(),
+        "wasi:http/types@0.2.0".to_string(),
+        "wasi:http/outgoing-handler@0.2.0".to_string(),
+        "wasi:http/proxy@0.2.0".to_string(),
+        "wasi:http/imports@0.2.0".to_string(),
+    ];
+    expected_exports.sort();
+
+    let mut exports = component_info.exports;
+    exports.sort();
+
+    assert_eq!(
+        exports, expected_exports,
+        "Expected exports to match:\nGot: {exports:?}\nExpected:\n{expected_exports:?}"
+    );
+    assert!(component_info.imports.is_empty(), "Should have no imports");
+}
+
+#[tokio::test]
+async fn test_layer_digest_verification() {
+    let (conf, mut layer) = WasmConfig::from_component("./tests/data/component.wasm", None)
+        .await
+        .expect("Should be able to parse component and create config");
+
+    conf.verify_layers(std::slice::from_ref(&layer))
+        .expect("Unmodified layer should verify");
+
+    let mut tampered = layer.data.to_vec();
+    tampered.push(0);
+    layer.data = tampered.into();
+    let err = conf
+        .verify_layers(std::slice::from_ref(&layer))
+        .expect_err("Tampered layer should not verify");
+    assert!(
+        matches!(err, Error::LayerDigestMismatch { .. }),
+        "Should have returned a layer digest mismatch error"
+    );
+
+    let err = conf
+        .verify_layers(&[])
+        .expect_err("Missing layer should not verify");
+    assert!(
+        matches!(err, Error::WrongLayerCount(0)),
+        "Should have returned a wrong layer count error"
+    );
+}
+
+/// Builds a small core module that imports `proc_exit` from WASI preview 1 and exports its memory
+fn wasi_module() -> Vec<u8> {
+    let mut module = wasm_encoder::Module::new();
+    let mut types = wasm_encoder::TypeSection::new();
+    types.ty().function([wasm_encoder::ValType::I32], []);
+    module.section(&types);
+    let mut imports = wasm_encoder::ImportSection::new();
+    imports.import(
+        "wasi_snapshot_preview1",
+        "proc_exit",
+        wasm_encoder::EntityType::Function(0),
+    );
+    module.section(&imports);
+    let mut memories = wasm_encoder::MemorySection::new();
+    memories.memory(wasm_encoder::MemoryType {
+        minimum: 1,
+        maximum: None,
+        memory64: false,
+        shared: false,
+        page_size_log2: None,
+    });
+    module.section(&memories);
+    let mut exports = wasm_encoder::ExportSection::new();
+    exports.export("memory", wasm_encoder::ExportKind::Memory, 0);
+    module.section(&exports);
+    module.finish()
+}
+
+async fn component_image_data() -> ImageData {
+    let (conf, layer) = WasmConfig::from_component("./tests/data/component.wasm", None)
+        .await
+        .expect("Should be able to parse component and create config");
+    ImageData {
+        layers: vec![layer],
+        digest: None,
+        config: conf
+            .to_config()
+            .expect("Should be able to serialize config"),
+        manifest: None,
+    }
+}
+
+#[tokio::test]
+async fn test_image_layout_round_trip() {
+    let dir = std::env::temp_dir().join(format!("oci-wasm-layout-{}", std::process::id()));
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+
+    let image_data = component_image_data().await;
+    let digest = write_image_layout(&dir, &image_data, Some("0.0.1"))
+        .await
+        .expect("Should be able to write image layout");
+    let (module_conf, module_layer) = WasmConfig::from_raw_module(wasi_module(), None)
+        .expect("Should be able to create module config");
+    let module_data = ImageData {
+        layers: vec![module_layer],
+        digest: None,
+        config: module_conf.to_config().unwrap(),
+        manifest: None,
+    };
+    write_image_layout(&dir, &module_data, Some("module"))
+        .await
+        .expect("Should be able to add a second artifact to the layout");
+
+    assert!(
+        read_image_layout(&dir, None).await.is_err(),
+        "Should require a ref name when multiple manifests are present"
+    );
+
+    let (data, conf) = read_image_layout(&dir, Some("0.0.1"))
+        .await
+        .expect("Should be able to read image layout");
+    assert_eq!(data.digest, Some(digest), "Should have the same digest");
+    assert_eq!(
+        data.layers[0].data, image_data.layers[0].data,
+        "Should have the same layer data"
+    );
+    assert_eq!(
+        data.config.data, image_data.config.data,
+        "Should have the same config data"
+    );
+    assert_eq!(conf.os, COMPONENT_OS, "Should have the right OS value set");
+
+    // Tampering with the layer should be caught when reading
+    let hex = data.layers[0]
+        .sha256_digest()
+        .trim_start_matches("sha256:")
+        .to_string();
+    tokio::fs::write(dir.join("blobs/sha256").join(hex), b"not wasm")
+        .await
+        .unwrap();
+    let err = match read_image_layout(&dir, Some("0.0.1")).await {
+        Ok(_) => panic!("Should not be able to read a tampered layout"),
+        Err(e) => e,
+    };
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+    assert!(
+        matches!(err, Error::LayerDigestMismatch { .. }),
+        "Should have returned a layer digest mismatch error"
+    );
+}
+
+#[tokio::test]
+async fn test_layout_digest_matches_registry() {
+    let registry = setup_registry()
+        .await
+        .expect("Should be able to start docker registry");
+    let registry_ip = registry
+        .get_host()
+        .await
+        .expect("Should be able to get ip for docker registry");
+    let registry_port = registry
+        .get_host_port_ipv4(DOCKER_REGISTRY_PORT)
+        .await
+        .expect("Should be able to get port for docker registry");
+    let registry_address = format!("{registry_ip}:{registry_port}");
+
+    let client = setup_client(registry_address.clone());
+    let auth = oci_client::secrets::RegistryAuth::Anonymous;
+    let image =
+        oci_client::Reference::try_from(format!("{registry_address}/layout/app:0.0.1")).unwrap();
+    let annotations = std::collections::BTreeMap::from([(
+        "org.opencontainers.image.version".to_string(),
+        "0.0.1".to_string(),
+    )]);
+
+    let (conf, layer) = WasmConfig::from_component("./tests/data/component.wasm", None)
+        .await
+        .expect("Should be able to parse component and create config");
+    client
+        .push(
+            &image,
+            &auth,
+            layer.clone(),
+            &conf,
+            Some(annotations.clone()),
+        )
+        .await
+        .expect("Should be able to push component");
+    let data = client
+        .pull(&image, &auth)
+        .await
+        .expect("Should be able to pull component");
+    let pulled_digest = data.digest.clone().expect("Should have a digest");
+
+    let archive_digest =
+        write_image_archive(std::io::sink(), layer, &conf, Some(annotations), None)
+            .expect("Should be able to write image archive");
+    assert_eq!(
+        archive_digest, pulled_digest,
+        "Archive should contain the same manifest that was pushed"
+    );
+
+    let dir = std::env::temp_dir().join(format!("oci-wasm-layout-digest-{}", std::process::id()));
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+    let layout_digest = write_image_layout(&dir, &data, None)
+        .await
+        .expect("Should be able to write image layout");
+    assert_eq!(
+        layout_digest, pulled_digest,
+        "Layout should contain the pulled manifest"
+    );
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+
+    let layout_digest = client
+        .pull_to_layout(&image, &auth, &dir, None)
+        .await
+        .expect("Should be able to pull to layout");
+    let (read, _) = read_image_layout(&dir, None)
+        .await
+        .expect("Should be able to read pulled layout");
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+    assert_eq!(layout_digest, pulled_digest);
+    assert_eq!(read.digest, Some(pulled_digest));
+}
+
+#[tokio::test]
+async fn test_image_archive_round_trip() {
+    let (conf, layer) = WasmConfig::from_component("./tests/data/component.wasm", None)
+        .await
+        .expect("Should be able to parse component and create config");
+    let annotations = std::collections::BTreeMap::from([(
+        "org.opencontainers.image.version".to_string(),
+        "0.0.1".to_string(),
+    )]);
+
+    let mut archive = Vec::new();
+    let digest = write_image_archive(
+        &mut archive,
+        layer.clone(),
+        &conf,
+        Some(annotations.clone()),
+        Some("0.0.1"),
+    )
+    .expect("Should be able to write image archive");
+    let mut second = Vec::new();
+    write_image_archive(
+        &mut second,
+        layer.clone(),
+        &conf,
+        Some(annotations.clone()),
+        Some("0.0.1"),
+    )
+    .unwrap();
+    assert_eq!(archive, second, "Archives should be reproducible");
+
+    let (manifest, read_conf, read_layer) =
+        read_image_archive(archive.as_slice(), None).expect("Should be able to read image archive");
+    assert_eq!(
+        read_layer.data, layer.data,
+        "Should have the same layer data"
+    );
+    assert_eq!(
+        read_conf.layer_digests, conf.layer_digests,
+        "Should have the same config"
+    );
+    assert_eq!(
+        manifest.annotations,
+        Some(annotations),
+        "Should preserve the manifest annotations"
+    );
+    assert_eq!(
+        manifest.media_type.as_deref(),
+        Some(WASM_MANIFEST_MEDIA_TYPE),
+        "Should have the proper manifest media type"
+    );
+    assert!(digest.starts_with("sha256:"), "Should return the digest");
+
+    assert!(
+        read_image_archive(archive.as_slice(), Some("missing")).is_err(),
+        "Should not find a manifest with an unknown ref name"
+    );
+}
+
+#[test]
+fn test_resolve_tag() {
+    let tags = [
+        "latest",
+        "0.1.0",
+        "v0.2.0",
+        "0.2.3",
+        "0.3.0-rc.1",
+        "1.3.1",
+        "1.3.4",
+        "1.4.0",
+        "main",
+    ];
+    let cases = [
+        ("^0.2", false, Some("0.2.3")),
+        ("~1.3.1", false, Some("1.3.4")),
+        ("^1", false, Some("1.4.0")),
+        ("=0.2.0", false, Some("v0.2.0")),
+        ("^0.3", false, None),
+        ("^0.3", true, Some("0.3.0-rc.1")),
+        ("^2", true, None),
+    ];
+    for (req, include_prerelease, expected) in cases {
+        let req = semver::VersionReq::parse(req).unwrap();
+        assert_eq!(
+            resolve_tag(tags, &req, include_prerelease),
+            expected,
+            "Should resolve {req} (include pre-releases: {include_prerelease}) correctly"
+        );
+    }
+
+    let tags = ["1.3.0-rc.1", "1.2.0"];
+    let cases = [
+        ("=1.3.0-rc.1", true, Some("1.3.0-rc.1")),
+        ("=1.3.0-rc.1", false, Some("1.3.0-rc.1")),
+        ("^1.3.0-rc.1", false, Some("1.3.0-rc.1")),
+        ("^1.2", false, Some("1.2.0")),
+        ("^1.2", true, Some("1.3.0-rc.1")),
+    ];
+    for (req, include_prerelease, expected) in cases {
+        let req = semver::VersionReq::parse(req).unwrap();
+        assert_eq!(
+            resolve_tag(tags, &req, include_prerelease),
+            expected,
+            "Should resolve {req} (include pre-releases: {include_prerelease}) correctly"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_config_builder() {
+    let component = Component::from_component("./tests/data/component.wasm")
+        .await
+        .expect("Should be able to parse component");
+    let (_, layer) = WasmConfig::from_component("./tests/data/component.wasm", None)
+        .await
+        .expect("Should be able to parse component and create config");
+    let created = chrono::DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+        .unwrap()
+        .to_utc();
+
+    let conf = WasmConfig::builder()
+        .created(created)
+        .author("Bugs Bunny")
+        .component(component)
+        .layer(&layer)
+        .build()
+        .expect("Should be able to build a valid config");
+    assert_eq!(conf.created, created, "Should have the given created time");
+    assert_eq!(conf.author.as_deref(), Some("Bugs Bunny"));
+    assert_eq!(conf.architecture, WASM_ARCHITECTURE);
+    assert_eq!(conf.os, COMPONENT_OS, "Should default to the component OS");
+    assert_eq!(conf.layer_digests, vec![layer.sha256_digest()]);
+
+    let err = WasmConfig::builder()
+        .architecture("amd64")
+        .os(COMPONENT_OS)
+        .layer_digest("sha256:nothex")
+        .build()
+        .expect_err("Should not be able to build an invalid config");
+    let Error::InvalidConfig(violations) = err else {
+        panic!("Should have returned an invalid config error, got {err:?}");
+    };
+    assert_eq!(
+        violations,
+        vec![
+            ConfigViolation::WrongArchitecture("amd64".to_string()),
+            ConfigViolation::MissingComponent(WasmOs::Wasip2),
+            ConfigViolation::MalformedLayerDigest("sha256:nothex".to_string()),
+        ],
+        "Should report every violation"
+    );
+}
+
+#[tokio::test]
+async fn test_reproducible_config() {
+    let created = chrono::DateTime::from_timestamp(1700000000, 0).unwrap();
+
+    for path in [
+        "./tests/data/component.wasm",
+        "./tests/data/binary_wit.wasm",
+    ] {
+        let build = || async {
+            let (conf, layer) = WasmConfig::from_component(path, None)
+                .await
+                .expect("Should be able to parse component and create config");
+            let conf = WasmConfig::builder()
+                .created(created)
+                .component(conf.component.expect("Should have component information"))
+                .layer(&layer)
+                .build()
+                .expect("Should be able to build config");
+            (conf, layer)
+        };
+        let (first, layer) = build().await;
+        let (second, _) = build().await;
+        let component = first.component.as_ref().unwrap();
+        assert!(
+            component.exports.is_sorted() && component.imports.is_sorted(),
+            "Exports and imports should be sorted"
+        );
+        assert_eq!(
+            first.to_config().unwrap().data,
+            second.to_config().unwrap().data,
+            "Configs should be identical"
+        );
+
+        let first_digest =
+            write_image_archive(std::io::sink(), layer.clone(), &first, None, None).unwrap();
+        let second_digest =
+            write_image_archive(std::io::sink(), layer, &second, None, None).unwrap();
+        assert_eq!(
+            first_digest, second_digest,
+            "Manifest digests should be identical"
+        );
+    }
+}
+
+#[test]
+fn test_strict_config_parsing() {
+    let bad = r#"{"created":"2024-01-01T00:00:00Z","architecture":"amd64","os":"linux","layerDigests":[]}"#;
+    WasmConfig::try_from(bad).expect("Lenient parsing should accept any well-formed config");
+    let err = WasmConfig::parse_strict(bad).expect_err("Strict parsing should reject bad config");
+    let Error::InvalidConfig(violations) = err else {
+        panic!("Should have returned an invalid config error, got {err:?}");
+    };
+    assert_eq!(
+        violations,
+        vec![
+            ConfigViolation::WrongArchitecture("amd64".to_string()),
+            ConfigViolation::UnknownOs("linux".to_string()),
+            ConfigViolation::MissingLayerDigests,
+        ],
+        "Should report every violation at once"
+    );
+
+    let missing_component = format!(
+        r#"{{"created":"2024-01-01T00:00:00Z","architecture":"wasm","os":"{COMPONENT_OS}","layerDigests":["sha256:{}"]}}"#,
+        "a".repeat(64)
+    );
+    let err = WasmConfig::parse_strict(missing_component)
+        .expect_err("Strict parsing should require component information for wasip2");
+    assert!(
+        matches!(err, Error::InvalidConfig(ref v) if v == &[ConfigViolation::MissingComponent(WasmOs::Wasip2)]),
+        "Should report the missing component, got {err:?}"
+    );
+
+    let valid = format!(
+        r#"{{"created":"2024-01-01T00:00:00Z","architecture":"wasm","os":"wasip1","layerDigests":["sha256:{}"]}}"#,
+        "a".repeat(64)
+    );
+    WasmConfig::parse_strict(valid).expect("Strict parsing should accept a valid module config");
+}
+
+#[test]
+fn test_wasm_os() {
+    for (value, os) in [
+        ("wasip1", WasmOs::Wasip1),
+        ("wasip2", WasmOs::Wasip2),
+        ("wasip3", WasmOs::Wasip3),
+        ("wasip9", WasmOs::Unknown("wasip9".to_string())),
+    ] {
+        let parsed: WasmOs = serde_json::from_str(&format!("\"{value}\""))
+            .expect("Should be able to deserialize any OS string");
+        assert_eq!(parsed, os, "Should parse {value} into the right variant");
+        assert_eq!(
+            serde_json::to_string(&parsed).expect("Should be able to serialize OS"),
+            format!("\"{value}\""),
+            "Should serialize back to the original string"
+        );
+    }
+    assert!(
+        WasmOs::Wasip3.is_component(),
+        "wasip3 should be a component"
+    );
+    assert!(!WasmOs::Wasip1.is_component(), "wasip1 should be a module");
+
+    let (config, _) = WasmConfig::from_raw_module(b"\0asm\x01\0\0\0".to_vec(), None)
+        .expect("Should build module config");
+    assert_eq!(config.os, WasmOs::Wasip1, "Modules should target wasip1");
+}
+
+#[tokio::test]
+async fn test_wasi_version_detection() {
+    let (config, _) = WasmConfig::from_component("./tests/data/component.wasm", None)
+        .await
+        .expect("Should be able to load component");
+    let range = config
+        .component
+        .as_ref()
+        .expect("Should have component information")
+        .wasi_versions()
+        .expect("Should detect WASI versions");
+    assert_eq!(
+        range.min,
+        semver::Version::new(0, 2, 0),
+        "Should have the right min"
+    );
+    assert_eq!(
+        range.max,
+        semver::Version::new(0, 2, 0),
+        "Should have the right max"
+    );
+    assert_eq!(
+        config.os,
+        WasmOs::Wasip2,
+        "Should detect a wasip2 component"
+    );
+
+    let mixed = Component {
+        exports: vec!["wasi:http/handler@0.3.0-rc-2025-09-16".to_string()],
+        imports: vec![
+            "example:thing/iface@1.0.0".to_string(),
+            "wasi:cli/environment@0.2.3".to_string(),
+            "wasi:io/streams".to_string(),
+        ],
+        ..Default::default()
+    };
+    let range = mixed.wasi_versions().expect("Should detect WASI versions");
+    assert_eq!(
+        range.min,
+        semver::Version::new(0, 2, 3),
+        "Should have the right min"
+    );
+    assert_eq!(range.max.minor, 3, "Should have the right max");
+    assert_eq!(
+        mixed.detect_os(),
+        WasmOs::Wasip3,
+        "Should detect a wasip3 component"
+    );
+
+    let config = WasmConfig::builder()
+        .component(mixed)
+        .layer_digest(format!("sha256:{}", "a".repeat(64)))
+        .build()
+        .expect("Should build config");
+    assert_eq!(
+        config.os,
+        WasmOs::Wasip3,
+        "Builder should use the detected OS"
+    );
+
+    let no_wasi = Component {
+        exports: vec![],
+        imports: vec!["example:thing/iface@1.0.0".to_string()],
+        ..Default::default()
+    };
+    assert!(
+        no_wasi.wasi_versions().is_none(),
+        "Should not detect any WASI versions"
+    );
+    assert_eq!(
+        no_wasi.detect_os(),
+        WasmOs::Wasip2,
+        "Should default to wasip2"
+    );
+}
+
+#[tokio::test]
+async fn test_wasm_kind_detection() {
+    let (config, _, kind) = WasmConfig::from_wasm("./tests/data/component.wasm", None)
+        .await
+        .expect("Should be able to load component");
+    assert_eq!(kind, WasmKind::Component, "Should detect a component");
+    assert_eq!(config.os, WasmOs::Wasip2, "Should set the component OS");
+    assert!(
+        config.component.is_some(),
+        "Should set component information"
+    );
+
+    let (config, _, kind) = WasmConfig::from_wasm("./tests/data/binary_wit.wasm", None)
+        .await
+        .expect("Should be able to load WIT package");
+    assert_eq!(kind, WasmKind::WitPackage, "Should detect a WIT package");
+    assert!(
+        config.component.is_some(),
+        "Should set component information"
+    );
+
+    // The smallest valid core module is just the header
+    let module = b"\0asm\x01\0\0\0".to_vec();
+    let (config, layer, kind) =
+        WasmConfig::from_raw_wasm(module.clone(), None).expect("Should be able to load module");
+    assert_eq!(kind, WasmKind::Module, "Should detect a module");
+    assert_eq!(config.os, WasmOs::Wasip1, "Should set the module OS");
+    assert!(
+        config.component.is_none(),
+        "Should not set component information"
+    );
+    assert_eq!(
+        layer.data.as_ref(),
+        module.as_slice(),
+        "Should keep the raw bytes"
+    );
+
+    let err = match WasmConfig::from_raw_wasm(b"not wasm at all".to_vec(), None) {
+        Ok(_) => panic!("Should not accept non-wasm data"),
+        Err(e) => e,
+    };
+    assert!(
+        matches!(err, Error::NotWasm),
+        "Should return a not wasm error, got {err:?}"
+    );
+}
+
+#[tokio::test]
+async fn test_component_target() {
+    let mut resolve = wit_parser::Resolve::default();
+    let pkg_id = resolve
+        .push_str(
+            "test.wit",
+            r#"
+            package example:http@0.1.0;
+
+            interface types {
+                type status = u16;
+            }
+            interface handler {
+                use types.{status};
+                handle: func() -> status;
+            }
+            world proxy {
+                import types;
+                export handler;
+            }
+            world app {
+                import types;
+                export handler;
+            }
+            "#,
+        )
+        .expect("Should be able to parse WIT");
+    let app = resolve
+        .select_world(&[pkg_id], Some("app"))
+        .expect("Should find app world");
+    let component = Component::from_world(&resolve, app).expect("Should load world");
+    assert_eq!(
+        component.target.as_deref(),
+        Some("example:http/proxy@0.1.0"),
+        "Should detect the world implemented by the component"
+    );
+
+    let raw = tokio::fs::read("./tests/data/binary_wit.wasm")
+        .await
+        .expect("Should be able to read WIT package");
+    let wit_component::DecodedWasm::WitPackage(resolve, pkg_id) =
+        wit_component::decode(&raw).expect("Should decode WIT package")
+    else {
+        panic!("Should be a WIT package");
+    };
+    let mut component = Component {
+        exports: vec!["wasi:http/incoming-handler@0.2.0".to_string()],
+        imports: vec![
+            "wasi:http/types@0.2.0".to_string(),
+            "wasi:io/streams@0.2.0".to_string(),
+        ],
+        ..Default::default()
+    };
+    let target = component
+        .detect_target(&resolve, pkg_id)
+        .expect("Package should exist");
+    assert_eq!(
+        target,
+        Some("wasi:http/proxy@0.2.0"),
+        "Should pick the proxy world over the imports world"
+    );
+
+    let (config, _) = WasmConfig::from_component("./tests/data/component.wasm", None)
+        .await
+        .expect("Should be able to load component");
+    let mut component = config.component.expect("Should have component information");
+    assert!(
+        component
+            .detect_target(&resolve, pkg_id)
+            .expect("Package should exist")
+            .is_none(),
+        "A component importing the filesystem should not implement the proxy world"
+    );
+    assert!(component.target.is_none(), "Should leave the target unset");
+
+    // A world that allows everything the compiled component imports, defined alongside the WASI
+    // packages the component was built against
+    let raw = tokio::fs::read("./tests/data/component.wasm")
+        .await
+        .expect("Should be able to read component");
+    let wit_component::DecodedWasm::Component(mut component_resolve, _) =
+        wit_component::decode(&raw).expect("Should decode component")
+    else {
+        panic!("Should be a component");
+    };
+    let imports = component
+        .imports
+        .iter()
+        .map(|name| format!("import {name};"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let server = component_resolve
+        .push_str(
+            "server.wit",
+            &format!(
+                "package example:server@0.1.0;
+                world server {{
+                    {imports}
+                    export wasi:http/incoming-handler@0.2.0;
+                }}"
+            ),
+        )
+        .expect("Should be able to parse WIT");
+    let (config, _) =
+        WasmConfig::from_raw_component_with_target(raw, None, &component_resolve, server)
+            .expect("Should be able to load component");
+    assert_eq!(
+        config
+            .component
+            .expect("Should have component information")
+            .target
+            .as_deref(),
+        Some("example:server/server@0.1.0"),
+        "Should detect the world a compiled component implements"
+    );
+
+    for mut component in [
+        Component::default(),
+        Component {
+            exports: vec!["my:custom/thing".to_string()],
+            ..Default::default()
+        },
+    ] {
+        assert!(
+            component
+                .detect_target(&resolve, pkg_id)
+                .expect("Package should exist")
+                .is_none(),
+            "Should not match a world without exports"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_world_conformance() {
+    let raw = tokio::fs::read("./tests/data/binary_wit.wasm")
+        .await
+        .expect("Should be able to read WIT package");
+    let wit_component::DecodedWasm::WitPackage(resolve, pkg_id) =
+        wit_component::decode(&raw).expect("Should decode WIT package")
+    else {
+        panic!("Should be a WIT package");
+    };
+    let proxy = resolve
+        .select_world(&[pkg_id], Some("proxy"))
+        .expect("Should find proxy world");
+
+    let (config, layer) = WasmConfig::from_component("./tests/data/component.wasm", None)
+        .await
+        .expect("Should be able to load component");
+    let report = config
+        .component
+        .as_ref()
+        .expect("Should have component information")
+        .satisfies_world(&resolve, proxy)
+        .expect("World should exist");
+    assert_eq!(
+        report.world, "wasi:http/proxy@0.2.0",
+        "Should name the world"
+    );
+    assert!(!report.is_conformant(), "Component should not conform");
+    assert!(
+        report.missing_exports.is_empty(),
+        "Component should export the incoming handler"
+    );
+    assert!(
+        report
+            .disallowed_imports
+            .contains(&"wasi:filesystem/types@0.2.0".to_string()),
+        "Should report the filesystem import, got {:?}",
+        report.disallowed_imports
+    );
+
+    let conforming = Component {
+        exports: vec!["wasi:http/incoming-handler@0.2.0".to_string()],
+        imports: vec!["wasi:http/types@0.2.0".to_string()],
+        ..Default::default()
+    };
+    assert!(
+        conforming
+            .satisfies_world(&resolve, proxy)
+            .expect("World should exist")
+            .is_conformant(),
+        "Component should conform"
+    );
+
+    // Exporting a newer patch release of WASI still provides everything the world exports, but
+    // importing one may need items the world doesn't provide
+    let patched = Component {
+        exports: vec!["wasi:http/incoming-handler@0.2.3".to_string()],
+        imports: vec!["wasi:http/types@0.2.0".to_string()],
+        ..Default::default()
+    };
+    let report = patched
+        .satisfies_world(&resolve, proxy)
+        .expect("World should exist");
+    assert!(
+        report.is_conformant(),
+        "Component exporting a newer patch version should conform, got {report:?}"
+    );
+    let newer_import = Component {
+        exports: vec!["wasi:http/incoming-handler@0.2.3".to_string()],
+        imports: vec!["wasi:http/types@0.2.3".to_string()],
+        ..Default::default()
+    };
+    let report = newer_import
+        .satisfies_world(&resolve, proxy)
+        .expect("World should exist");
+    assert_eq!(
+        report.disallowed_imports,
+        vec!["wasi:http/types@0.2.3".to_string()],
+        "Should not allow importing a newer version than the world provides"
+    );
+
+    let mut newer_resolve = wit_parser::Resolve::default();
+    let newer_pkg = newer_resolve
+        .push_str(
+            "test.wit",
+            r#"
+            package example:http@0.2.3;
+
+            interface types {
+                type status = u16;
+            }
+            interface handler {
+                use types.{status};
+                handle: func() -> status;
+            }
+            world proxy {
+                import types;
+                export handler;
+            }
+            "#,
+        )
+        .expect("Should parse WIT");
+    let newer_proxy = newer_resolve
+        .select_world(&[newer_pkg], Some("proxy"))
+        .expect("Should find proxy world");
+    let older = Component {
+        exports: vec!["example:http/handler@0.2.0".to_string()],
+        imports: vec!["example:http/types@0.2.0".to_string()],
+        ..Default::default()
+    };
+    let report = older
+        .satisfies_world(&newer_resolve, newer_proxy)
+        .expect("World should exist");
+    assert_eq!(
+        report.missing_exports,
+        vec!["example:http/handler@0.2.3".to_string()],
+        "Should not accept an export older than the world requires"
+    );
+    assert!(
+        report.disallowed_imports.is_empty(),
+        "Should allow importing an older version than the world provides, got {report:?}"
+    );
+    let incompatible = Component {
+        exports: vec!["wasi:http/incoming-handler@0.3.0".to_string()],
+        imports: vec!["wasi:http/types@0.3.0".to_string()],
+        ..Default::default()
+    };
+    let report = incompatible
+        .satisfies_world(&resolve, proxy)
+        .expect("World should exist");
+    assert_eq!(
+        report.missing_exports,
+        vec!["wasi:http/incoming-handler@0.2.0".to_string()]
+    );
+    assert_eq!(
+        report.disallowed_imports,
+        vec!["wasi:http/types@0.3.0".to_string()]
+    );
+
+    // The check happens before anything is sent, so this never reaches the registry
+    let client = WasmClient::new(oci_client::Client::default()).with_required_world(resolve, proxy);
+    let image: oci_client::Reference = "localhost:1/wasm/proxy:v1"
+        .parse()
+        .expect("Should parse reference");
+    let err = match client
+        .push(
+            &image,
+            &oci_client::secrets::RegistryAuth::Anonymous,
+            layer,
+            &config,
+            None,
+        )
+        .await
+    {
+        Ok(_) => panic!("Should refuse to push a non-conforming component"),
+        Err(e) => e,
+    };
+    assert!(
+        matches!(err, Error::WorldConformance(ref report) if !report.disallowed_imports.is_empty()),
+        "Should return a conformance error, got {err:?}"
+    );
+}
+
+#[tokio::test]
+async fn test_host_compatibility() {
+    let (config, _) = WasmConfig::from_component("./tests/data/component.wasm", None)
+        .await
+        .expect("Should be able to load component");
+    let imports = config
+        .component
+        .as_ref()
+        .expect("Should have component information")
+        .imports
+        .clone();
+
+    // A newer patch release of every interface is compatible
+    let host = HostCapabilities::new(imports.iter().map(|i| i.replace("@0.2.0", "@0.2.3")));
+    assert!(
+        config.check_host(&host).is_compatible(),
+        "Newer compatible versions should satisfy every import"
+    );
+
+    // A host that only provides 0.3 or an older patch release is not
+    let host = HostCapabilities::new(imports.iter().map(|i| i.replace("@0.2.0", "@0.3.0")));
+    assert_eq!(
+        config.check_host(&host).unsatisfied_imports,
+        imports,
+        "Incompatible versions should not satisfy any import"
+    );
+    let older = HostCapabilities::new(["wasi:cli/environment@0.1.9"]);
+    assert!(
+        !older.provides("wasi:cli/environment@0.2.0"),
+        "Older versions are incompatible"
+    );
+    let pre = HostCapabilities::new(["wasi:http/types@0.3.0-rc-2025-09-16"]);
+    assert!(
+        pre.provides("wasi:http/types@0.3.0-rc-2025-09-16"),
+        "Identical pre-releases are compatible"
+    );
+    assert!(
+        !pre.provides("wasi:http/types@0.3.0-rc-2025-08-15"),
+        "Different pre-releases are incompatible"
+    );
+
+    // A wasi:http/proxy host doesn't provide the filesystem
+    let raw = tokio::fs::read("./tests/data/binary_wit.wasm")
+        .await
+        .expect("Should be able to read WIT package");
+    let wit_component::DecodedWasm::WitPackage(resolve, pkg_id) =
+        wit_component::decode(&raw).expect("Should decode WIT package")
+    else {
+        panic!("Should be a WIT package");
+    };
+    let proxy = resolve
+        .select_world(&[pkg_id], Some("proxy"))
+        .expect("Should find proxy world");
+    let host = HostCapabilities::from_world(&resolve, proxy).expect("World should exist");
+    let report = config.check_host(&host);
+    assert!(
+        !report.is_compatible(),
+        "Proxy hosts should not run this component"
+    );
+    assert!(
+        report
+            .unsatisfied_imports
+            .contains(&"wasi:filesystem/types@0.2.0".to_string()),
+        "Should report the filesystem import, got {:?}",
+        report.unsatisfied_imports
+    );
+}
+
+#[tokio::test]
+async fn test_component_metadata() {
+    let raw = tokio::fs::read("./tests/data/component.wasm")
+        .await
+        .expect("Should be able to read component");
+    let component =
+        Component::from_raw_component_with_metadata(&raw).expect("Should decode component");
+    let metadata = component
+        .metadata
+        .as_ref()
+        .expect("Should have generated metadata");
+
+    let handler = metadata
+        .exports
+        .iter()
+        .find(|i| i.name == "wasi:http/incoming-handler@0.2.0")
+        .expect("Should have the incoming handler export");
+    assert_eq!(
+        handler.functions,
+        vec![FunctionMetadata {
+            name: "handle".to_string(),
+            signature: "func(request: incoming-request, response-out: response-outparam)"
+                .to_string(),
+        }],
+        "Should record the handler function and its signature"
+    );
+    let types = metadata
+        .imports
+        .iter()
+        .find(|i| i.name == "wasi:http/types@0.2.0")
+        .expect("Should have the http types import");
+    assert!(
+        types.resources.contains(&"fields".to_string()),
+        "Should record resource types, got {:?}",
+        types.resources
+    );
+    assert!(
+        types
+            .functions
+            .iter()
+            .any(|f| f.name == "[constructor]fields"),
+        "Should record resource functions"
+    );
+    assert_eq!(
+        metadata.imports.len(),
+        component.imports.len(),
+        "Should have metadata for every import"
+    );
+
+    let expected = metadata.clone();
+    let (config, layer) = WasmConfig::from_raw_component_with_metadata(raw, None)
+        .expect("Should create config with metadata");
+    assert_eq!(config.layer_digests, vec![layer.sha256_digest()]);
+    let data = config.to_config().expect("Should serialize config").data;
+    let parsed = WasmConfig::parse_strict(&data).expect("Should parse config with metadata");
+    assert_eq!(
+        parsed.component.and_then(|c| c.metadata),
+        Some(expected),
+        "Metadata should round trip through the config"
+    );
+
+    let (plain, _) = WasmConfig::from_component("./tests/data/component.wasm", None)
+        .await
+        .expect("Should be able to load component");
+    let data = plain.to_config().expect("Should serialize config").data;
+    assert!(
+        !String::from_utf8_lossy(&data).contains("metadata"),
+        "Metadata should be omitted unless requested"
+    );
+}
+
+#[test]
+fn test_world_item_metadata() {
+    let mut resolve = wit_parser::Resolve::default();
+    let pkg_id = resolve
+        .push_str(
+            "test.wit",
+            r#"
+            package example:app@0.1.0;
+
+            interface types {
+                record point {
+                    x: u32,
+                    y: u32,
+                }
+            }
+            interface run {
+                run: func();
+            }
+            world app {
+                use types.{point};
+                type id = u64;
+                import log: func(msg: string, at: point);
+                import run;
+                export run: func() -> result<id>;
+            }
+            "#,
+        )
+        .expect("Should be able to parse WIT");
+    let app = resolve
+        .select_world(&[pkg_id], Some("app"))
+        .expect("Should find app world");
+    let metadata =
+        oci_wasm::ComponentMetadata::from_world(&resolve, app).expect("Should load world");
+
+    let imported_interfaces = metadata
+        .imports
+        .iter()
+        .map(|i| i.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        imported_interfaces,
+        vec!["example:app/run@0.1.0", "example:app/types@0.1.0"],
+        "Should list imported interfaces by their qualified names"
+    );
+    assert_eq!(
+        metadata.imported_functions,
+        vec![FunctionMetadata {
+            name: "log".to_string(),
+            signature: "func(msg: string, at: point)".to_string(),
+        }],
+        "Should list freestanding imported functions separately"
+    );
+    assert_eq!(
+        metadata.exported_functions,
+        vec![FunctionMetadata {
+            name: "run".to_string(),
+            signature: "func() -> result<id>".to_string(),
+        }],
+        "Should tell the run function apart from the run interface"
+    );
+    assert!(
+        metadata.exports.is_empty(),
+        "Should not export any interfaces"
+    );
+    assert_eq!(
+        metadata.imported_types,
+        vec![
+            TypeMetadata {
+                name: "id".to_string(),
+                kind: "type".to_string(),
+            },
+            TypeMetadata {
+                name: "point".to_string(),
+                kind: "record".to_string(),
+            },
+        ],
+        "Should list world types with the kind they resolve to"
+    );
+
+    let component = Component::from_world(&resolve, app).expect("Should load world");
+    assert_eq!(
+        component.imports,
+        vec!["example:app/run@0.1.0", "example:app/types@0.1.0"],
+        "Should only list interfaces as imports"
+    );
+    assert!(
+        component.exports.is_empty(),
+        "Should not list the run function as an exported interface"
+    );
+    assert_eq!(component.imported_functions, vec!["log"]);
+    assert_eq!(component.exported_functions, vec!["run"]);
+    assert_eq!(component.imported_types, vec!["id", "point"]);
+    assert!(component.exported_types.is_empty());
+
+    let host = HostCapabilities::from_world(&resolve, app).expect("Should load world");
+    assert!(
+        !host.provides("log"),
+        "Should not treat imported functions as interfaces"
+    );
+    assert!(
+        host.provides_function("log"),
+        "Should provide the functions the world imports"
+    );
+    assert!(
+        component.check_host(&host).is_compatible(),
+        "Should be compatible with a host for its own world"
+    );
+    let without_log = HostCapabilities::new(&component.imports);
+    assert_eq!(
+        component.check_host(&without_log).unsatisfied_imports,
+        vec!["log"],
+        "Should report imported functions the host doesn't provide"
+    );
+    assert!(
+        component
+            .check_host(&without_log.with_functions(["log"]))
+            .is_compatible(),
+        "Should be compatible once the host provides the function"
+    );
+    let report = component
+        .satisfies_world(&resolve, app)
+        .expect("World should exist");
+    assert!(
+        report.is_conformant(),
+        "Should conform to its own world: {report:?}"
+    );
+
+    let interface_only = Component {
+        exports: vec!["run".to_string()],
+        imports: component.imports.clone(),
+        imported_functions: component.imported_functions.clone(),
+        ..Default::default()
+    };
+    let report = interface_only
+        .satisfies_world(&resolve, app)
+        .expect("World should exist");
+    assert_eq!(
+        report.missing_exports,
+        vec!["run"],
+        "An interface named run should not satisfy the run function export"
+    );
+}
+
+#[test]
+fn test_module_metadata() {
+    let (config, _) =
+        WasmConfig::from_raw_module(wasi_module(), None).expect("Should build module config");
+    let module = config
+        .module
+        .as_ref()
+        .expect("Should have module information");
+    assert_eq!(
+        module.imports,
+        vec![ModuleImport {
+            module: "wasi_snapshot_preview1".to_string(),
+            name: "proc_exit".to_string(),
+            kind: ModuleItemKind::Func,
+        }],
+        "Should record the module's imports"
+    );
+    assert_eq!(
+        module.exports,
+        vec![ModuleExport {
+            name: "memory".to_string(),
+            kind: ModuleItemKind::Memory,
+        }],
+        "Should record the module's exports"
+    );
+    assert_eq!(
+        module.wasi,
+        Some(WasiSnapshot::Preview1),
+        "Should detect the WASI snapshot"
+    );
+
+    let data = config.to_config().expect("Should serialize config").data;
+    let parsed = WasmConfig::parse_strict(&data).expect("Should parse config with module info");
+    assert_eq!(
+        parsed.module.as_ref(),
+        Some(module),
+        "Module information should round trip through the config"
+    );
+
+    let component = std::fs::read("./tests/data/component.wasm").expect("Should read component");
+    let err = match WasmConfig::from_raw_module(component, None) {
+        Ok(_) => panic!("Should not accept a component as a module"),
+        Err(e) => e,
+    };
+    assert!(
+        matches!(err, Error::ModuleDecode(_)),
+        "Should return a module decode error, got {err:?}"
+    );
+}
+
+#[test]
+fn test_signing_keys() {
+    for key in [
+        SigningKey::generate_ecdsa_p256(),
+        SigningKey::generate_ed25519(),
+    ] {
+        let pem = key.to_pkcs8_pem().expect("Should be able to encode key");
+        let loaded = SigningKey::from_pkcs8_pem(&pem).expect("Should be able to load key");
+        assert_eq!(loaded.verifying_key(), key.verifying_key());
+
+        let public_pem = key
+            .verifying_key()
+            .to_public_key_pem()
+            .expect("Should be able to encode public key");
+        let public = oci_wasm::VerifyingKey::from_public_key_pem(&public_pem)
+            .expect("Should be able to load public key");
+        assert_eq!(public, key.verifying_key());
+
+        let signature = key.sign(b"payload");
+        assert!(public.verify(b"payload", &signature), "Should verify");
+        assert!(
+            !public.verify(b"tampered", &signature),
+            "Should not verify a different payload"
+        );
+    }
+
+    let err = SigningKey::from_pkcs8_pem("not a key").expect_err("Should reject invalid keys");
+    assert!(
+        matches!(err, Error::InvalidKey(_)),
+        "Should return an invalid key error, got {err:?}"
+    );
+}
+
+#[test]
+fn test_embedded_signature() {
+    let raw = std::fs::read("./tests/data/component.wasm").expect("Should be able to read file");
+    let key = SigningKey::generate_ed25519();
+    let (conf, layer) = WasmConfig::from_raw_component_signed(raw.clone(), None, &key)
+        .expect("Should be able to sign component");
+    assert_eq!(
+        conf.signer_key_id,
+        Some(key.verifying_key().key_id()),
+        "Should record the signer key id"
+    );
+    assert_eq!(conf.layer_digests, vec![layer.sha256_digest()]);
+    assert!(key.verifying_key().verify_embedded(&layer.data));
+    assert!(!SigningKey::generate_ed25519()
+        .verifying_key()
+        .verify_embedded(&layer.data));
+    assert!(
+        !key.verifying_key().verify_embedded(&raw),
+        "Should not verify an unsigned component"
+    );
+    let signed_component = Component::from_raw_component(&layer.data)
+        .expect("Signed component should still be a valid component");
+    assert_eq!(signed_component.exports, conf.component.unwrap().exports);
+
+    // Re-signing replaces the existing signature
+    let other = SigningKey::generate_ed25519();
+    let (_, resigned) = WasmConfig::from_raw_component_signed(layer.data.to_vec(), None, &other)
+        .expect("Should be able to re-sign component");
+    assert!(other.verifying_key().verify_embedded(&resigned.data));
+    assert!(!key.verifying_key().verify_embedded(&resigned.data));
+
+    let mut tampered = layer.data.to_vec();
+    *tampered.last_mut().unwrap() ^= 0xff;
+    assert!(
+        !key.verifying_key().verify_embedded(&tampered),
+        "Should not verify a modified component"
+    );
+
+    let err = WasmConfig::from_raw_component_signed(raw, None, &SigningKey::generate_ecdsa_p256())
+        .expect_err("Should reject ECDSA keys");
+    assert!(
+        matches!(err, Error::InvalidKey(_)),
+        "Should return an invalid key error, got {err:?}"
+    );
+}
+
+#[test]
+fn test_wasm_platform_resolver() {
+    let entry = |digest: &str, arch: Arch, os: Os| oci_client::manifest::ImageIndexEntry {
+        media_type: WASM_MANIFEST_MEDIA_TYPE.to_string(),
+        digest: digest.to_string(),
+        size: 0,
+        platform: Some(oci_client::manifest::Platform {
+            architecture: arch,
+            os,
+            os_version: None,
+            os_features: None,
+            variant: None,
+            features: None,
+        }),
+        annotations: None,
+    };
+    let manifests = vec![
+        entry("sha256:linux", Arch::Amd64, Os::Linux),
+        entry("sha256:module", Arch::Wasm, Os::from("wasip1")),
+        entry("sha256:component", Arch::Wasm, Os::from("wasip2")),
+    ];
+
+    assert_eq!(
+        wasm_platform_resolver(&manifests).as_deref(),
+        Some("sha256:component"),
+        "Should prefer the wasip2 entry"
+    );
+    assert_eq!(
+        wasm_platform_resolver(&manifests[..2]).as_deref(),
+        Some("sha256:module"),
+        "Should fall back to the wasip1 entry"
+    );
+    assert_eq!(
+        wasm_platform_resolver(&manifests[..1]),
+        None,
+        "Should not select non-wasm entries"
+    );
+
+    let resolver = wasm_platform_resolver_with_preference(vec![WasmOs::Wasip3, WasmOs::Wasip1]);
+    assert_eq!(
+        resolver(&manifests).as_deref(),
+        Some("sha256:module"),
+        "Should follow the configured preference order"
+    );
+
+    // Make sure the default client can be constructed with the resolver
+    let _client = WasmClient::new_with_defaults();
 }
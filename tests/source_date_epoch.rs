@@ -0,0 +1,27 @@
+//! `SOURCE_DATE_EPOCH` is read from the process environment, so these checks live in their own
+//! test binary where setting it can't affect any other test
+
+use oci_wasm::{Error, WasmConfig};
+
+#[test]
+fn test_source_date_epoch() {
+    std::env::remove_var("SOURCE_DATE_EPOCH");
+    WasmConfig::default_created()
+        .expect("Should fall back to the current time when SOURCE_DATE_EPOCH isn't set");
+
+    std::env::set_var("SOURCE_DATE_EPOCH", "1700000000");
+    let created = WasmConfig::default_created().expect("Should be able to parse SOURCE_DATE_EPOCH");
+    assert_eq!(
+        created.timestamp(),
+        1700000000,
+        "Should use SOURCE_DATE_EPOCH as the created time"
+    );
+
+    std::env::set_var("SOURCE_DATE_EPOCH", "yesterday");
+    let err = WasmConfig::default_created()
+        .expect_err("Should reject a SOURCE_DATE_EPOCH that isn't a timestamp");
+    assert!(
+        matches!(err, Error::InvalidSourceDateEpoch(ref epoch) if epoch == "yesterday"),
+        "Should return an invalid SOURCE_DATE_EPOCH error, got {err:?}"
+    );
+}